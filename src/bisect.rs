@@ -0,0 +1,310 @@
+use crate::error::Error;
+use crate::{read_head, Repo, EMPTY_REF};
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::process::Command;
+
+const ORIG_HEAD_FILE: &str = "orig_head";
+const BAD_FILE: &str = "bad";
+const GOOD_FILE: &str = "good";
+const SKIP_FILE: &str = "skip";
+
+// Exit code of a bisect run command telling the commit can't be tested.
+const SKIP_EXIT_CODE: i32 = 125;
+
+/// State of a binary search for the commit which introduced a regression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BisectOutcome {
+    /// Both a good and a bad commit are needed to start the search.
+    Waiting,
+    /// A commit is restored into the working directory to be tested and marked.
+    Testing { commit: String, remaining: usize },
+    /// The first bad commit.
+    Found(String),
+    /// The first bad commit is one of these, the others were skipped.
+    Inconclusive(Vec<String>),
+}
+
+// Marks collected so far. Stored as separate files in `.get/bisect` until the bisect is reset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BisectState {
+    // HEAD before the bisect started.
+    orig_head: String,
+    bad: Option<String>,
+    good: Vec<String>,
+    skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl Repo {
+    /// Starts a bisect, optionally with known bad and good commits. Bisect goes on until reset
+    /// even between invocations.
+    pub fn bisect_start(&self, bad: Option<&str>, good: &[&str]) -> Result<BisectOutcome, Error> {
+        self.check_no_merge_state()?;
+        if self.bisect_dir_path().is_dir() {
+            return Err(Error::MergeInProgress("bisect"));
+        }
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        // Candidates are restored over the working directory.
+        self.check_clean()?;
+
+        // Make sure all the marks are commits before saving anything.
+        for digest in bad.iter().chain(good.iter()) {
+            let _ = self.read_commit_object(digest.to_string())?;
+        }
+
+        let state = BisectState {
+            orig_head: head,
+            bad: bad.map(|b| b.to_owned()),
+            good: good.iter().map(|g| g.to_string()).collect(),
+            skipped: Vec::new(),
+        };
+        self.write_bisect_state(&state)?;
+
+        self.bisect_next(&state)
+    }
+
+    /// Marks a commit as good, meaning the regression is not there yet.
+    pub fn bisect_good(&self, digest: &str) -> Result<BisectOutcome, Error> {
+        self.bisect_mark(digest, Mark::Good)
+    }
+
+    /// Marks a commit as bad, meaning the regression is already there.
+    pub fn bisect_bad(&self, digest: &str) -> Result<BisectOutcome, Error> {
+        self.bisect_mark(digest, Mark::Bad)
+    }
+
+    /// Marks a commit which can't be tested, so another one is picked instead.
+    pub fn bisect_skip(&self, digest: &str) -> Result<BisectOutcome, Error> {
+        self.bisect_mark(digest, Mark::Skip)
+    }
+
+    /// Ends the bisect and restores the commit which was HEAD before it started.
+    pub fn bisect_reset(&self) -> Result<String, Error> {
+        let state = self.read_bisect_state()?.ok_or(Error::NoBisectInProgress)?;
+
+        self.restore(state.orig_head.as_str())?;
+        fs::remove_dir_all(self.bisect_dir_path())?;
+
+        Ok(state.orig_head)
+    }
+
+    /// Runs a command on every candidate to mark it automatically until the search is over.
+    /// Exit code 0 marks a commit as good, 125 skips it and any other code up to 127 marks it as
+    /// bad. Higher codes and signals stop the run.
+    pub fn bisect_run(&self, command: &[String]) -> Result<BisectOutcome, Error> {
+        let state = self.read_bisect_state()?.ok_or(Error::NoBisectInProgress)?;
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| Error::BisectRunFailed(String::from("no command given")))?;
+
+        let mut outcome = self.bisect_next(&state)?;
+
+        while let BisectOutcome::Testing { commit, .. } = outcome {
+            let status = Command::new(program)
+                .args(args)
+                .current_dir(self.work_dir.as_path())
+                .status()?;
+
+            let mark = match status.code() {
+                Some(0) => Mark::Good,
+                Some(SKIP_EXIT_CODE) => Mark::Skip,
+                Some(1..=127) => Mark::Bad,
+                Some(code) => {
+                    let reason = format!("command exited with {code} on {commit}");
+                    return Err(Error::BisectRunFailed(reason));
+                }
+                None => {
+                    let reason = format!("command was killed on {commit}");
+                    return Err(Error::BisectRunFailed(reason));
+                }
+            };
+
+            outcome = self.bisect_mark(commit.as_str(), mark)?;
+        }
+
+        Ok(outcome)
+    }
+
+    fn bisect_mark(&self, digest: &str, mark: Mark) -> Result<BisectOutcome, Error> {
+        let mut state = self.read_bisect_state()?.ok_or(Error::NoBisectInProgress)?;
+
+        let _ = self.read_commit_object(digest.to_owned())?;
+
+        match mark {
+            Mark::Good => state.good.push(digest.to_owned()),
+            Mark::Bad => state.bad = Some(digest.to_owned()),
+            Mark::Skip => state.skipped.push(digest.to_owned()),
+        }
+        self.write_bisect_state(&state)?;
+
+        self.bisect_next(&state)
+    }
+
+    // Picks the next commit to test, the one splitting the remaining candidates most evenly, and
+    // restores it. Candidates are the ancestors of the bad commit which are not ancestors of any
+    // good one.
+    fn bisect_next(&self, state: &BisectState) -> Result<BisectOutcome, Error> {
+        let Some(bad) = state.bad.as_ref() else {
+            return Ok(BisectOutcome::Waiting);
+        };
+        if state.good.is_empty() {
+            return Ok(BisectOutcome::Waiting);
+        }
+
+        let mut candidates = self.ancestors(bad.as_str())?;
+        for good in state.good.iter() {
+            for digest in self.ancestors(good.as_str())? {
+                candidates.remove(&digest);
+            }
+        }
+
+        // Bad commit reachable from a good one leaves nothing to search.
+        if !candidates.contains(bad) {
+            return Err(Error::BisectBadIsAncestorOfGood(bad.clone()));
+        }
+
+        let skipped: HashSet<&String> = state.skipped.iter().collect();
+        let testable: Vec<&String> = candidates
+            .iter()
+            .filter(|c| *c != bad && !skipped.contains(c))
+            .collect();
+
+        let reached = self.reached_candidates(&candidates)?;
+        let best = testable.iter().max_by_key(|c| {
+            let r = reached[**c];
+            // Ties go to the smallest digest, so the pick doesn't depend on the set order.
+            (r.min(candidates.len() - r), Reverse(**c))
+        });
+
+        let Some(commit) = best else {
+            let mut left: Vec<String> = candidates
+                .iter()
+                .filter(|c| *c == bad || skipped.contains(c))
+                .cloned()
+                .collect();
+
+            if left.len() == 1 {
+                return Ok(BisectOutcome::Found(bad.clone()));
+            }
+
+            left.sort();
+            return Ok(BisectOutcome::Inconclusive(left));
+        };
+
+        let commit = commit.to_string();
+        self.restore(commit.as_str())?;
+
+        Ok(BisectOutcome::Testing {
+            commit,
+            remaining: testable.len(),
+        })
+    }
+
+    // Number of candidates reachable from every candidate, itself included. Parents of every
+    // candidate are read once and the reachable sets, kept as bitsets, are built from the parent
+    // ones.
+    fn reached_candidates<'a>(
+        &self,
+        candidates: &'a HashSet<String>,
+    ) -> Result<HashMap<&'a String, usize>, Error> {
+        let commits: Vec<&String> = candidates.iter().collect();
+        let index: HashMap<&String, usize> =
+            commits.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+
+        let mut parents = Vec::with_capacity(commits.len());
+        for c in commits.iter() {
+            let candidate_parents: Vec<usize> = self
+                .commit_parents(c.as_str())?
+                .iter()
+                .filter_map(|p| index.get(p).copied())
+                .collect();
+            parents.push(candidate_parents);
+        }
+
+        let words = commits.len().div_ceil(64);
+        let mut reached: Vec<Option<Vec<u64>>> = vec![None; commits.len()];
+        for start in 0..commits.len() {
+            // Parents are done before their children, history has no cycles.
+            let mut stack = vec![(start, false)];
+            while let Some((i, parents_done)) = stack.pop() {
+                if reached[i].is_some() {
+                    continue;
+                }
+
+                if !parents_done {
+                    stack.push((i, true));
+                    stack.extend(parents[i].iter().map(|p| (*p, false)));
+                    continue;
+                }
+
+                let mut bits = vec![0u64; words];
+                bits[i / 64] |= 1 << (i % 64);
+                for p in parents[i].iter().filter_map(|p| reached[*p].as_ref()) {
+                    for (b, pb) in bits.iter_mut().zip(p) {
+                        *b |= pb;
+                    }
+                }
+                reached[i] = Some(bits);
+            }
+        }
+
+        Ok(commits
+            .into_iter()
+            .zip(reached)
+            .map(|(c, bits)| {
+                let count = bits.iter().flatten().map(|b| b.count_ones() as usize).sum();
+                (c, count)
+            })
+            .collect())
+    }
+
+    fn write_bisect_state(&self, state: &BisectState) -> Result<(), Error> {
+        let dir = self.bisect_dir_path();
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(ORIG_HEAD_FILE), state.orig_head.as_str())?;
+        fs::write(dir.join(BAD_FILE), state.bad.as_deref().unwrap_or_default())?;
+        fs::write(dir.join(GOOD_FILE), state.good.join("\n"))?;
+        fs::write(dir.join(SKIP_FILE), state.skipped.join("\n"))?;
+
+        Ok(())
+    }
+
+    fn read_bisect_state(&self) -> Result<Option<BisectState>, Error> {
+        let dir = self.bisect_dir_path();
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+
+        let read_lines = |name: &str| -> Result<Vec<String>, Error> {
+            Ok(fs::read_to_string(dir.join(name))?
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_owned())
+                .collect())
+        };
+
+        Ok(Some(BisectState {
+            orig_head: fs::read_to_string(dir.join(ORIG_HEAD_FILE))?
+                .trim()
+                .to_owned(),
+            bad: read_lines(BAD_FILE)?.pop(),
+            good: read_lines(GOOD_FILE)?,
+            skipped: read_lines(SKIP_FILE)?,
+        }))
+    }
+}
@@ -0,0 +1,143 @@
+use crate::date::format_timestamp;
+use crate::diff::{diff_lines, Edit};
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::Repo;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+// Length of a digest prefix shown in blame output.
+const SHORT_DIGEST_LEN: usize = 8;
+
+/// A line of a file with the commit which last changed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    pub timestamp: u64,
+    /// Numbered from one.
+    pub line_number: usize,
+    pub content: String,
+}
+
+impl Repo {
+    /// Attributes every line of a file in a commit to the commit that last changed it. History
+    /// is followed through first parents, lines are carried back as long as the line diff
+    /// matches them with the parent version. Path is either absolute or relative to the working
+    /// directory root.
+    pub fn blame(&self, path: &Path, digest: &str) -> Result<Vec<BlameLine>, Error> {
+        let path = self.relative_path(path);
+
+        let blob = self
+            .file_blob(digest, &path)?
+            .ok_or_else(|| Error::FileNotFound(path.clone(), digest.to_owned()))?;
+        let content = self.read_blob_content(blob.as_str())?;
+        let lines: Vec<&str> = content.lines().collect();
+
+        // Lines still to attribute as pairs of a line index in the current version and in the
+        // blamed one.
+        let mut pending: Vec<(usize, usize)> = (0..lines.len()).map(|i| (i, i)).collect();
+        let mut origins: Vec<String> = vec![String::new(); lines.len()];
+
+        let mut commit = digest.to_owned();
+        let mut blob = blob;
+        while !pending.is_empty() {
+            let parent = self.commit_parents(commit.as_str())?.into_iter().next();
+            let parent_blob = match parent.as_deref() {
+                Some(p) => self.file_blob(p, &path)?,
+                None => None,
+            };
+
+            let (Some(parent), Some(parent_blob)) = (parent, parent_blob) else {
+                // File appeared in this commit, so all the remaining lines are its own.
+                for (_, original) in pending.drain(..) {
+                    origins[original] = commit.clone();
+                }
+                break;
+            };
+
+            // File is the same in the parent, nothing to attribute here.
+            if parent_blob == blob {
+                commit = parent;
+                continue;
+            }
+
+            let old_content = self.read_blob_content(parent_blob.as_str())?;
+            let new_content = self.read_blob_content(blob.as_str())?;
+            let old_lines: Vec<&str> = old_content.lines().collect();
+            let new_lines: Vec<&str> = new_content.lines().collect();
+
+            let to_parent: HashMap<usize, usize> = diff_lines(&old_lines, &new_lines)
+                .into_iter()
+                .filter_map(|e| match e {
+                    Edit::Equal(old, new) => Some((new, old)),
+                    _ => None,
+                })
+                .collect();
+
+            let mut carried = Vec::new();
+            for (current, original) in pending.drain(..) {
+                match to_parent.get(&current) {
+                    Some(old) => carried.push((*old, original)),
+                    None => origins[original] = commit.clone(),
+                }
+            }
+
+            pending = carried;
+            commit = parent;
+            blob = parent_blob;
+        }
+
+        let mut metadata: HashMap<String, (String, u64)> = HashMap::new();
+        let mut blamed = Vec::with_capacity(lines.len());
+        for (i, (line, origin)) in lines.iter().zip(origins).enumerate() {
+            if !metadata.contains_key(&origin) {
+                let object = self.read_commit_object(origin.clone())?;
+                let author = object.author()?.to_owned();
+                let Object::Commit { timestamp, .. } = object else {
+                    return Err(not_a(paths::COMMITS_DIR, origin.as_str()));
+                };
+                metadata.insert(origin.clone(), (author, timestamp.as_secs()));
+            }
+            let (author, timestamp) = metadata[&origin].clone();
+
+            blamed.push(BlameLine {
+                commit: origin,
+                author,
+                timestamp,
+                line_number: i + 1,
+                content: line.to_string(),
+            });
+        }
+
+        Ok(blamed)
+    }
+
+    // Blob digest of a file in a commit, None if there is no such file. Only the trees along
+    // the path are read.
+    fn file_blob(&self, digest: &str, path: &Path) -> Result<Option<String>, Error> {
+        let commit = self.read_commit_object(digest.to_owned())?;
+
+        Ok(self
+            .path_entry(&commit, path)?
+            .filter(|(kind, _)| kind == paths::BLOB_DIR)
+            .map(|(_, blob)| blob))
+    }
+}
+
+impl fmt::Display for BlameLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} {} {}) {}",
+            &self.commit[..SHORT_DIGEST_LEN.min(self.commit.len())],
+            self.author,
+            format_timestamp(self.timestamp),
+            self.line_number,
+            self.content
+        )
+    }
+}
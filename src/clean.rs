@@ -0,0 +1,142 @@
+use crate::diff::{self, ChangeKind};
+use crate::error::Error;
+use crate::ignore::IgnoreRules;
+use crate::{read_head, Repo, DEFAULT_IGNORE};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What `clean` removes besides untracked files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CleanOptions {
+    /// Only list what would be removed.
+    pub dry_run: bool,
+    /// Also remove untracked files in directories without tracked ones, and directories left
+    /// empty, files in them are kept otherwise.
+    pub directories: bool,
+    /// Also remove ignored files, and ignored directories along with `directories`.
+    pub ignored: bool,
+}
+
+// What a clean walk needs to tell which paths go, all of them relative to the root.
+struct Sweep<'a> {
+    rules: IgnoreRules,
+    tracked_dirs: HashSet<&'a Path>,
+    untracked: HashSet<PathBuf>,
+    directories: bool,
+}
+
+impl Repo {
+    /// Removes files which are not in HEAD and not ignored, the ones status shows as added.
+    /// Returns removed paths relative to the root in order, directories end with a slash.
+    pub fn clean(&self, options: CleanOptions) -> Result<Vec<PathBuf>, Error> {
+        let head = read_head(self.work_dir.as_path())?;
+        let tracked = self.commit_files(head.as_str())?;
+
+        // The same comparison status makes. Ignored files are only looked at when they are
+        // removed too, but repo's own files never are.
+        let rules = if options.ignored {
+            IgnoreRules::new(DEFAULT_IGNORE.iter().copied())
+        } else {
+            self.ignore_rules()?
+        };
+        let working = self.working_files_with(&rules)?;
+        let untracked = diff::diff_files(&tracked, &working)
+            .into_iter()
+            .filter(|c| c.kind == ChangeKind::Added)
+            .map(|c| c.path)
+            .collect();
+
+        let sweep = Sweep {
+            rules,
+            tracked_dirs: tracked.keys().flat_map(|p| p.ancestors().skip(1)).collect(),
+            untracked,
+            directories: options.directories,
+        };
+        let mut removed = Vec::new();
+        self.sweep_dir(&sweep, Path::new(""), &mut removed)?;
+
+        if !options.dry_run {
+            for path in removed.iter() {
+                let full_path = self.work_dir.join(path);
+                if full_path.is_dir() {
+                    // Nothing but removed files and empty directories is left in there.
+                    fs::remove_dir_all(full_path)?;
+                } else {
+                    fs::remove_file(full_path)?;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    // Collects untracked files of a directory with tracked files, descending into untracked
+    // directories only along with `directories`.
+    fn sweep_dir(&self, sweep: &Sweep, dir: &Path, found: &mut Vec<PathBuf>) -> Result<(), Error> {
+        for (path, is_dir) in self.dir_entries(dir)? {
+            if sweep.rules.is_ignored(&path, is_dir) {
+                continue;
+            }
+
+            if !is_dir {
+                if sweep.untracked.contains(&path) {
+                    found.push(path);
+                }
+            } else if sweep.tracked_dirs.contains(path.as_path()) {
+                self.sweep_dir(sweep, &path, found)?;
+            } else if sweep.directories {
+                self.sweep_untracked_dir(sweep, &path, found)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Collects an untracked directory as a whole when nothing is left in it after its untracked
+    // files are removed. Otherwise only those files and the directories which end up empty are
+    // collected, so ignored files inside are kept.
+    fn sweep_untracked_dir(
+        &self,
+        sweep: &Sweep,
+        dir: &Path,
+        found: &mut Vec<PathBuf>,
+    ) -> Result<bool, Error> {
+        let mut inner = Vec::new();
+        let mut emptied = true;
+        for (path, is_dir) in self.dir_entries(dir)? {
+            if sweep.rules.is_ignored(&path, is_dir) {
+                emptied = false;
+            } else if is_dir {
+                emptied &= self.sweep_untracked_dir(sweep, &path, &mut inner)?;
+            } else if sweep.untracked.contains(&path) {
+                inner.push(path);
+            } else {
+                emptied = false;
+            }
+        }
+
+        if emptied {
+            // Joining an empty path adds a trailing slash.
+            found.push(dir.join(""));
+        } else {
+            found.append(&mut inner);
+        }
+
+        Ok(emptied)
+    }
+
+    // Directory entries by name with a flag telling directories apart.
+    fn dir_entries(&self, dir: &Path) -> Result<Vec<(PathBuf, bool)>, Error> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(self.work_dir.join(dir))? {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            entries.push((path, entry.file_type()?.is_dir()));
+        }
+        entries.sort();
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,676 @@
+use crate::error::Error;
+use crate::identity::Identity;
+use crate::paths::{self, CONFIG_FILE};
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::Deserialize;
+use toml::Spanned;
+use toml_edit::{Array, Document, Item, Value};
+use users::get_current_username;
+
+const GLOBAL_CONFIG_DIR: &str = "get";
+const GLOBAL_CONFIG_FILE: &str = "config.toml";
+
+const AUTHOR_KEY: &str = "author";
+const IGNORE_KEY: &str = "ignore";
+const KNOWN_KEYS: &[&str] = &[AUTHOR_KEY, IGNORE_KEY];
+
+// Unknown keys this close to a known one are reported as its typos.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+const AUTHOR_VAR: &str = "GET_AUTHOR";
+const AUTHOR_NAME_VAR: &str = "GET_AUTHOR_NAME";
+const AUTHOR_EMAIL_VAR: &str = "GET_AUTHOR_EMAIL";
+// Ignore patterns separated by colons.
+const IGNORE_VAR: &str = "GET_IGNORE";
+
+// Values set by a single layer, missing ones are taken from the layers below.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Layer {
+    ignore: Option<Vec<String>>,
+    author: Option<String>,
+    // Only set by environment variables, overriding a part of the author.
+    #[serde(skip)]
+    author_name: Option<String>,
+    #[serde(skip)]
+    author_email: Option<String>,
+}
+
+/// Config file to read or edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// `~/.config/get/config.toml`, shared by all the repos.
+    Global,
+    /// `.get.toml` in the repo root.
+    Repo,
+}
+
+/// Where a config value comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Config file, the repo one is relative to the root.
+    File(PathBuf),
+    /// Environment variable.
+    Env(&'static str),
+    /// Nothing sets the value.
+    Default,
+}
+
+/// Value of a config key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValue {
+    String(String),
+    List(Vec<String>),
+}
+
+/// Config value in effect with its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: ConfigValue,
+    pub source: ConfigSource,
+}
+
+/// Ignore patterns set by a single config layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IgnoreList {
+    pub(crate) source: ConfigSource,
+    pub(crate) patterns: Vec<String>,
+}
+
+/// Configuration merged from the global config file, the repo one and `GET_*` environment
+/// variables. Each layer overrides values of the previous ones, ignore patterns of all the
+/// layers are applied in the same order, so the later ones win.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    pub(crate) ignore: Vec<IgnoreList>,
+    pub(crate) author: Identity,
+    // Every value in effect, the ones overridden by higher layers are not here.
+    entries: Vec<ConfigEntry>,
+}
+
+// Layer with the file it was read from, no scope for environment variables.
+struct ScopedLayer {
+    scope: Option<ConfigScope>,
+    path: PathBuf,
+    layer: Layer,
+}
+
+impl Config {
+    pub(crate) fn resolve(work_dir: &Path) -> Result<Config, Error> {
+        let layers = read_layers(Some(work_dir), None)?;
+
+        Config::from_layers(layers)
+    }
+
+    fn from_layers(layers: Vec<ScopedLayer>) -> Result<Config, Error> {
+        let mut ignore = Vec::new();
+        let mut author = None;
+
+        for l in layers {
+            let source = |var| match l.scope {
+                Some(_) => ConfigSource::File(l.path.clone()),
+                None => ConfigSource::Env(var),
+            };
+
+            if let Some(patterns) = l.layer.ignore {
+                ignore.push(IgnoreList {
+                    source: source(IGNORE_VAR),
+                    patterns,
+                });
+            }
+            if let Some(a) = l.layer.author {
+                author = Some((a.parse::<Identity>()?, source(AUTHOR_VAR)));
+            }
+            if let Some(name) = l.layer.author_name {
+                let email = author.as_ref().and_then(|(a, _)| a.email.clone());
+                let identity = Identity::new(name.as_str(), email.as_deref())?;
+                author = Some((identity, ConfigSource::Env(AUTHOR_NAME_VAR)));
+            }
+            if let Some(email) = l.layer.author_email {
+                let name = author
+                    .as_ref()
+                    .map_or_else(|| default_author().name, |(a, _)| a.name.clone());
+                let identity = Identity::new(name.as_str(), Some(email.as_str()))?;
+                author = Some((identity, ConfigSource::Env(AUTHOR_EMAIL_VAR)));
+            }
+        }
+
+        let (author, author_source) =
+            author.unwrap_or_else(|| (default_author(), ConfigSource::Default));
+
+        let mut entries = vec![ConfigEntry {
+            key: AUTHOR_KEY.to_owned(),
+            value: ConfigValue::String(author.to_string()),
+            source: author_source,
+        }];
+        entries.extend(ignore.iter().map(|list| ConfigEntry {
+            key: IGNORE_KEY.to_owned(),
+            value: ConfigValue::List(list.patterns.clone()),
+            source: list.source.clone(),
+        }));
+
+        Ok(Config {
+            ignore,
+            author,
+            entries,
+        })
+    }
+}
+
+/// Config values in effect for a directory, inside a repo or not, with where they come from.
+/// With a scope lists everything set in that file only.
+pub fn config_entries(
+    cur_dir: &Path,
+    scope: Option<ConfigScope>,
+) -> Result<Vec<ConfigEntry>, Error> {
+    let work_dir = paths::repo_dir(cur_dir);
+
+    let Some(scope) = scope else {
+        let layers = read_layers(work_dir.as_deref().ok(), None)?;
+        return Ok(Config::from_layers(layers)?.entries);
+    };
+
+    let work_dir = match scope {
+        ConfigScope::Global => None,
+        ConfigScope::Repo => Some(work_dir?),
+    };
+
+    let mut entries = Vec::new();
+    for l in read_layers(work_dir.as_deref(), Some(scope))? {
+        let source = ConfigSource::File(l.path);
+        if let Some(a) = l.layer.author {
+            entries.push(ConfigEntry {
+                key: AUTHOR_KEY.to_owned(),
+                value: ConfigValue::String(a),
+                source: source.clone(),
+            });
+        }
+        if let Some(patterns) = l.layer.ignore {
+            entries.push(ConfigEntry {
+                key: IGNORE_KEY.to_owned(),
+                value: ConfigValue::List(patterns),
+                source,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Values of a key in effect, see `config_entries`. Every ignore layer has its own entry.
+pub fn config_get(
+    cur_dir: &Path,
+    scope: Option<ConfigScope>,
+    key: &str,
+) -> Result<Vec<ConfigEntry>, Error> {
+    check_key(key)?;
+
+    Ok(config_entries(cur_dir, scope)?
+        .into_iter()
+        .filter(|e| e.key == key)
+        .collect())
+}
+
+/// Sets a key in a config file keeping the rest of the file, comments included, as is. Author
+/// takes exactly one value, ignore takes a list of patterns.
+pub fn config_set(
+    cur_dir: &Path,
+    scope: ConfigScope,
+    key: &str,
+    values: &[String],
+) -> Result<(), Error> {
+    let mut value = match key {
+        AUTHOR_KEY => match values {
+            [author] => Value::from(author.parse::<Identity>()?.to_string()),
+            _ => return Err(Error::InvalidConfigValue(key.to_owned())),
+        },
+        IGNORE_KEY => Value::from(values.iter().collect::<Array>()),
+        _ => return Err(Error::UnknownConfigKey(key.to_owned())),
+    };
+
+    edit_config_file(cur_dir, scope, |doc| {
+        // Comments around the old value stay with the new one.
+        if let Some(old) = doc.get_mut(key).and_then(|i| i.as_value_mut()) {
+            *value.decor_mut() = old.decor().clone();
+        }
+        doc[key] = Item::Value(value);
+    })
+}
+
+/// Removes a key from a config file, nothing happens if it is not set there. Unknown keys can
+/// be removed too, to clean up the ones reported as misspelled.
+pub fn config_unset(cur_dir: &Path, scope: ConfigScope, key: &str) -> Result<(), Error> {
+    edit_config_file(cur_dir, scope, |doc| {
+        doc.remove(key);
+    })
+}
+
+fn check_key(key: &str) -> Result<(), Error> {
+    if KNOWN_KEYS.contains(&key) {
+        return Ok(());
+    }
+
+    Err(Error::UnknownConfigKey(key.to_owned()))
+}
+
+// Applies an edit to a config file, creating it if needed. The result is checked to be a valid
+// config before it's written.
+fn edit_config_file(
+    cur_dir: &Path,
+    scope: ConfigScope,
+    edit: impl FnOnce(&mut Document),
+) -> Result<(), Error> {
+    let path = match scope {
+        ConfigScope::Global => global_config_path().ok_or(Error::NoGlobalConfig)?,
+        ConfigScope::Repo => paths::repo_dir(cur_dir)?.join(CONFIG_FILE),
+    };
+
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::IoError(e)),
+    };
+    let mut doc: Document = content.parse().map_err(|e: toml_edit::TomlError| {
+        config_error(&path, content.as_str(), e.span(), e.message())
+    })?;
+
+    edit(&mut doc);
+
+    let content = doc.to_string();
+    let _ = parse_layer(&path, content.as_str())?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, content)?;
+
+    Ok(())
+}
+
+// Global config location, `$XDG_CONFIG_HOME/get/config.toml` falling back to
+// `~/.config/get/config.toml`.
+fn global_config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .map(|d| d.join(GLOBAL_CONFIG_DIR).join(GLOBAL_CONFIG_FILE))
+}
+
+// Layers from the lowest precedence: the global file, the repo one if there is a repo, then
+// environment variables. With a scope only that file is read.
+fn read_layers(
+    work_dir: Option<&Path>,
+    scope: Option<ConfigScope>,
+) -> Result<Vec<ScopedLayer>, Error> {
+    let mut layers = Vec::new();
+
+    if scope.is_none_or(|s| s == ConfigScope::Global) {
+        if let Some(path) = global_config_path() {
+            if let Some(layer) = read_layer(&path)? {
+                layers.push(ScopedLayer {
+                    scope: Some(ConfigScope::Global),
+                    path,
+                    layer,
+                });
+            }
+        }
+    }
+
+    if let Some(work_dir) = work_dir.filter(|_| scope.is_none_or(|s| s == ConfigScope::Repo)) {
+        if let Some(layer) = read_layer(&work_dir.join(CONFIG_FILE))? {
+            // Repo config is shown relative to the root the same way ignore files are.
+            layers.push(ScopedLayer {
+                scope: Some(ConfigScope::Repo),
+                path: PathBuf::from(CONFIG_FILE),
+                layer,
+            });
+        }
+    }
+
+    if scope.is_none() {
+        let vars = env::vars_os()
+            .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)));
+        layers.push(ScopedLayer {
+            scope: None,
+            path: PathBuf::new(),
+            layer: env_layer(vars),
+        });
+    }
+
+    Ok(layers)
+}
+
+fn env_layer(vars: impl IntoIterator<Item = (String, String)>) -> Layer {
+    let mut layer = Layer::default();
+
+    for (key, value) in vars {
+        match key.as_str() {
+            AUTHOR_VAR => layer.author = Some(value),
+            AUTHOR_NAME_VAR => layer.author_name = Some(value),
+            AUTHOR_EMAIL_VAR => layer.author_email = Some(value),
+            IGNORE_VAR => layer.ignore = Some(value.split(':').map(|p| p.to_owned()).collect()),
+            _ => (),
+        }
+    }
+
+    layer
+}
+
+// Reads a config file, None if there is no such file.
+fn read_layer(path: &Path) -> Result<Option<Layer>, Error> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::IoError(e)),
+    };
+
+    parse_layer(path, content.as_str()).map(Some)
+}
+
+// Parses a config file content checking values are of the right type. Unknown keys are only
+// warned about, they may be meant for another version and are kept when the file is edited.
+fn parse_layer(path: &Path, content: &str) -> Result<Layer, Error> {
+    let error = |e: toml::de::Error| config_error(path, content, e.span(), e.message());
+
+    let keys: BTreeMap<Spanned<String>, Spanned<toml::Value>> =
+        toml::from_str(content).map_err(error)?;
+    for (key, value) in keys.iter() {
+        if key.get_ref() == AUTHOR_KEY {
+            if let toml::Value::String(author) = value.get_ref() {
+                if let Err(e) = author.parse::<Identity>() {
+                    let message = e.to_string();
+                    return Err(config_error(path, content, Some(value.span()), &message));
+                }
+            }
+        }
+
+        if !KNOWN_KEYS.contains(&key.get_ref().as_str()) {
+            let (line, column) = position(content, Some(key.span()));
+            let message = unknown_key_message(key.get_ref());
+            warn!("config {}:{line}:{column}: {message}", path.display());
+        }
+    }
+
+    toml::from_str(content).map_err(error)
+}
+
+fn unknown_key_message(key: &str) -> String {
+    match suggest_key(key) {
+        Some(known) => format!("unknown key `{key}`, did you mean `{known}`?"),
+        None => format!(
+            "unknown key `{key}`, known keys are {}",
+            KNOWN_KEYS.join(", ")
+        ),
+    }
+}
+
+// Error pointing to the line and column where the span starts, the start of the file if there
+// is no span.
+fn config_error(path: &Path, content: &str, span: Option<Range<usize>>, message: &str) -> Error {
+    let (line, column) = position(content, span);
+
+    Error::ConfigError {
+        path: path.to_owned(),
+        line,
+        column,
+        message: message.to_owned(),
+    }
+}
+
+// Line and column where the span starts, both numbered from one.
+fn position(content: &str, span: Option<Range<usize>>) -> (usize, usize) {
+    let offset = span.map_or(0, |s| s.start.min(content.len()));
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+// Known key closest to a misspelled one, if it is close enough to be a typo.
+fn suggest_key(key: &str) -> Option<&'static str> {
+    KNOWN_KEYS
+        .iter()
+        .map(|k| (edit_distance(key, k), *k))
+        .filter(|(d, _)| *d <= MAX_SUGGESTION_DISTANCE)
+        .min()
+        .map(|(_, k)| k)
+}
+
+// Number of single character insertions, deletions and substitutions turning one string into
+// another (Levenshtein distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+
+    row[b.len()]
+}
+
+fn default_author() -> Identity {
+    let name = get_current_username()
+        .unwrap_or_else(|| {
+            warn!("couldn't fetch user name, default user name used instead");
+            OsString::from("unknown author")
+        })
+        .into_string()
+        .unwrap_or(String::from("unknown author"));
+
+    Identity { name, email: None }
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "{var}"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+// Values are shown the way they are written in TOML.
+impl fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::String(s) => write!(f, "{}", Value::from(s.as_str())),
+            Self::List(items) => write!(f, "{}", items.iter().collect::<Array>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let layer = |author: Option<&str>, ignore: Option<&[&str]>| Layer {
+            author: author.map(|a| a.to_owned()),
+            ignore: ignore.map(|i| i.iter().map(|p| p.to_string()).collect()),
+            ..Default::default()
+        };
+        let global = PathBuf::from("/home/me/.config/get/config.toml");
+
+        let config = Config::from_layers(vec![
+            ScopedLayer {
+                scope: Some(ConfigScope::Global),
+                path: global.clone(),
+                layer: layer(Some("Global"), Some(&["*.log"])),
+            },
+            ScopedLayer {
+                scope: Some(ConfigScope::Repo),
+                path: PathBuf::from(CONFIG_FILE),
+                layer: layer(None, Some(&["target"])),
+            },
+            ScopedLayer {
+                scope: None,
+                path: PathBuf::new(),
+                layer: env_layer([(String::from("GET_UNKNOWN"), String::from("value"))]),
+            },
+        ])
+        .unwrap();
+        assert!(config.author.to_string() == "Global");
+        assert!(
+            config.ignore
+                == vec![
+                    IgnoreList {
+                        source: ConfigSource::File(global.clone()),
+                        patterns: vec![String::from("*.log")],
+                    },
+                    IgnoreList {
+                        source: ConfigSource::File(PathBuf::from(CONFIG_FILE)),
+                        patterns: vec![String::from("target")],
+                    },
+                ]
+        );
+        assert!(config.entries[0].source == ConfigSource::File(global.clone()));
+
+        let vars = [
+            (String::from(AUTHOR_VAR), String::from("Env")),
+            (String::from(IGNORE_VAR), String::from("a:b")),
+        ];
+        let config = Config::from_layers(vec![
+            ScopedLayer {
+                scope: Some(ConfigScope::Global),
+                path: global,
+                layer: layer(Some("Global"), None),
+            },
+            ScopedLayer {
+                scope: None,
+                path: PathBuf::new(),
+                layer: env_layer(vars),
+            },
+        ])
+        .unwrap();
+        assert!(config.author.to_string() == "Env");
+        assert!(config.entries[0].source == ConfigSource::Env(AUTHOR_VAR));
+        assert!(config.ignore[0].patterns == vec!["a", "b"]);
+        assert!(config.ignore[0].source == ConfigSource::Env(IGNORE_VAR));
+
+        let config = Config::from_layers(Vec::new()).unwrap();
+        assert!(config.ignore.is_empty());
+        assert!(config.author == default_author());
+        assert!(config.entries[0].source == ConfigSource::Default);
+    }
+
+    #[test]
+    fn test_author_parts() {
+        let env = |vars: &[(&str, &str)]| ScopedLayer {
+            scope: None,
+            path: PathBuf::new(),
+            layer: env_layer(vars.iter().map(|(k, v)| (k.to_string(), v.to_string()))),
+        };
+        let repo = ScopedLayer {
+            scope: Some(ConfigScope::Repo),
+            path: PathBuf::from(CONFIG_FILE),
+            layer: Layer {
+                author: Some(String::from("Jane Doe <jane@example.com>")),
+                ..Default::default()
+            },
+        };
+
+        let config = Config::from_layers(vec![repo, env(&[(AUTHOR_NAME_VAR, "J. Doe")])]).unwrap();
+        assert!(config.author.to_string() == "J. Doe <jane@example.com>");
+        assert!(config.entries[0].source == ConfigSource::Env(AUTHOR_NAME_VAR));
+
+        let config = Config::from_layers(vec![env(&[
+            (AUTHOR_VAR, "Jane Doe"),
+            (AUTHOR_EMAIL_VAR, "jd@example.com"),
+        ])])
+        .unwrap();
+        assert!(config.author.to_string() == "Jane Doe <jd@example.com>");
+
+        let config = Config::from_layers(vec![env(&[(AUTHOR_EMAIL_VAR, "<bad>")])]);
+        assert!(matches!(config, Err(Error::InvalidIdentity(_))));
+    }
+
+    #[test]
+    fn test_value_display() {
+        let value = ConfigValue::String(String::from("Jane Doe"));
+        assert!(value.to_string() == "\"Jane Doe\"");
+
+        let value = ConfigValue::List(vec![String::from("*.log"), String::from("target")]);
+        assert!(value.to_string() == "[\"*.log\", \"target\"]");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let path = Path::new(CONFIG_FILE);
+        let position = |content: &str| match parse_layer(path, content) {
+            Err(Error::ConfigError {
+                line,
+                column,
+                message,
+                ..
+            }) => (line, column, message),
+            other => panic!("expected config error, got {other:?}"),
+        };
+
+        let (line, column, _) = position("author = \"Jane\"\nignore = \"*.log\"\n");
+        assert!((line, column) == (2, 10));
+
+        let (line, column, _) = position("author = \"Jane\"\nignore = [\n");
+        assert!((line, column) == (3, 1));
+
+        let (line, column, message) = position("author = \"Jane <jane\"\n");
+        assert!((line, column) == (1, 10));
+        assert!(message.starts_with("Jane <jane is not a valid identity"));
+
+        assert!(parse_layer(path, "author = \"Jane <jane@example.com>\"\n").is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        // Unknown keys are skipped, known ones are still read.
+        let layer = parse_layer(
+            Path::new(CONFIG_FILE),
+            "ignore = [\"*.log\"]\nautor = \"Jane\"\n",
+        );
+        let layer = layer.unwrap();
+        assert!(layer.ignore == Some(vec![String::from("*.log")]));
+        assert!(layer.author.is_none());
+
+        assert!(unknown_key_message("autor") == "unknown key `autor`, did you mean `author`?");
+        assert!(
+            unknown_key_message("colour") == "unknown key `colour`, known keys are author, ignore"
+        );
+        assert!(position("# settings\n\ncolour = true\n", Some(12..18)) == (3, 1));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert!(edit_distance("author", "author") == 0);
+        assert!(edit_distance("autor", "author") == 1);
+        assert!(edit_distance("ingore", "ignore") == 2);
+        assert!(edit_distance("", "abc") == 3);
+        assert!(suggest_key("ignores") == Some(IGNORE_KEY));
+        assert!(suggest_key("remotes").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::error::Error;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+// Time passed since the unix epoch, which is what gets stored in objects and the reflog.
+pub(crate) fn since_epoch(time: SystemTime) -> Result<Duration, Error> {
+    time.duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::TimeBeforeEpoch)
+}
+
+// Formats unix timestamp as a UTC date and time, like `2023-04-08 13:42:49 +0000`. We don't need
+// time zones yet, so there is no reason to pull a whole date time library for that.
+pub(crate) fn format_timestamp(secs: u64) -> String {
+    let days = (secs / SECONDS_PER_DAY) as i64;
+    let rem = secs % SECONDS_PER_DAY;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} +0000",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+// Parses a UTC date as `YYYY-MM-DD`, optionally followed by `HH:MM` or `HH:MM:SS`, or a plain unix
+// timestamp. None if the date is not valid.
+pub(crate) fn parse_date(date: &str) -> Option<u64> {
+    let date = date.trim();
+    if !date.is_empty() && date.chars().all(|c| c.is_ascii_digit()) {
+        return date.parse().ok();
+    }
+
+    let (day, time) = date.split_once([' ', 'T']).unwrap_or((date, "00:00"));
+
+    let day: Vec<&str> = day.split('-').collect();
+    let [year, month, day] = day.as_slice() else {
+        return None;
+    };
+    let (year, month, day) = (
+        year.parse::<i64>().ok()?,
+        month.parse::<u32>().ok()?,
+        day.parse::<u32>().ok()?,
+    );
+
+    let time: Vec<u64> = time
+        .split(':')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<_>>()?;
+    let (hours, minutes, seconds) = match time.as_slice() {
+        [h, m] => (*h, *m, 0),
+        [h, m, s] => (*h, *m, *s),
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    // Round trip rejects dates like February 30th.
+    if days < 0 || civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    Some(days as u64 * SECONDS_PER_DAY + hours * 3600 + minutes * 60 + seconds)
+}
+
+// Converts a number of days since unix epoch to a proleptic Gregorian calendar date. See
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days for the explanation.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+// Converts a proleptic Gregorian calendar date to a number of days since unix epoch. See
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil for the explanation.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from(if month > 2 { month - 3 } else { month + 9 });
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_timestamp, parse_date};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert!(format_timestamp(0) == "1970-01-01 00:00:00 +0000");
+        assert!(format_timestamp(1680961369) == "2023-04-08 13:42:49 +0000");
+        assert!(format_timestamp(951782400) == "2000-02-29 00:00:00 +0000");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert!(parse_date("1970-01-01") == Some(0));
+        assert!(parse_date("2023-04-08 13:42:49") == Some(1680961369));
+        assert!(parse_date("2023-04-08T13:42") == Some(1680961320));
+        assert!(parse_date("2000-02-29") == Some(951782400));
+        assert!(parse_date("1680961369") == Some(1680961369));
+
+        assert!(parse_date("2023-02-30").is_none());
+        assert!(parse_date("2023-04-08 24:00").is_none());
+        assert!(parse_date("yesterday").is_none());
+        assert!(parse_date("").is_none());
+    }
+}
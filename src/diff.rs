@@ -0,0 +1,430 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+const CONTEXT_LINES: usize = 3;
+
+/// Kind of change made to a path between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    Modified,
+}
+
+/// A path changed between two snapshots with its blob digests before and after the change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: PathBuf,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match self {
+            Self::Added => "A",
+            Self::Deleted => "D",
+            Self::Modified => "M",
+        };
+
+        write!(f, "{letter}")
+    }
+}
+
+/// Single step of a line script turning old lines into new ones. Holds indexes of the lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+// Compares two flattened snapshots (path to blob digest) and lists changed paths ordered by path.
+pub(crate) fn diff_files(
+    old: &BTreeMap<PathBuf, String>,
+    new: &BTreeMap<PathBuf, String>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for (path, old_digest) in old.iter() {
+        match new.get(path) {
+            None => changes.push(Change {
+                kind: ChangeKind::Deleted,
+                path: path.clone(),
+                old: Some(old_digest.clone()),
+                new: None,
+            }),
+            Some(new_digest) if new_digest != old_digest => changes.push(Change {
+                kind: ChangeKind::Modified,
+                path: path.clone(),
+                old: Some(old_digest.clone()),
+                new: Some(new_digest.clone()),
+            }),
+            Some(_) => (),
+        }
+    }
+
+    for (path, new_digest) in new.iter() {
+        if !old.contains_key(path) {
+            changes.push(Change {
+                kind: ChangeKind::Added,
+                path: path.clone(),
+                old: None,
+                new: Some(new_digest.clone()),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    changes
+}
+
+// Shortest edit script between two sequences of lines (Myers' algorithm in linear space).
+pub(crate) fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+    diff_range(old, new, (0, old.len()), (0, new.len()), &mut edits);
+
+    edits
+}
+
+// Appends the script for a range of old lines against a range of new ones. Lines common at both
+// ends are matched right away, the rest is split where the shortest script crosses its middle
+// and both halves are diffed on their own. So only two rows of the search are kept at a time
+// instead of one for every edit.
+fn diff_range(
+    old: &[&str],
+    new: &[&str],
+    (mut x0, mut x1): (usize, usize),
+    (mut y0, mut y1): (usize, usize),
+    edits: &mut Vec<Edit>,
+) {
+    while x0 < x1 && y0 < y1 && old[x0] == new[y0] {
+        edits.push(Edit::Equal(x0, y0));
+        x0 += 1;
+        y0 += 1;
+    }
+
+    let (end_x, end_y) = (x1, y1);
+    while x0 < x1 && y0 < y1 && old[x1 - 1] == new[y1 - 1] {
+        x1 -= 1;
+        y1 -= 1;
+    }
+
+    if x0 == x1 {
+        edits.extend((y0..y1).map(Edit::Insert));
+    } else if y0 == y1 {
+        edits.extend((x0..x1).map(Edit::Delete));
+    } else {
+        let (x, y) = split_point(&old[x0..x1], &new[y0..y1]);
+        diff_range(old, new, (x0, x0 + x), (y0, y0 + y), edits);
+        diff_range(old, new, (x0 + x, x1), (y0 + y, y1), edits);
+    }
+
+    edits.extend((x1..end_x).zip(y1..end_y).map(|(x, y)| Edit::Equal(x, y)));
+}
+
+// Point the shortest script passes through halfway, found by searching from both ends at once
+// until the searches meet. Sequences must differ at both ends, so the point is never one of the
+// ends and both halves are shorter.
+fn split_point(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+
+    // Furthest x reached on every diagonal k = x - y, forward from the start and backward from
+    // the end. Diagonals run from -m to n, with a sentinel past both of them.
+    let offset = m + 1;
+    let ix = |k: isize| (k + offset) as usize;
+    let mut forward = vec![-1; (n + m + 3) as usize];
+    let mut backward = vec![isize::MAX; (n + m + 3) as usize];
+    forward[ix(0)] = 0;
+    backward[ix(delta)] = n;
+
+    // Diagonals reached so far by each search.
+    let (mut fmin, mut fmax) = (0, 0);
+    let (mut bmin, mut bmax) = (delta, delta);
+
+    loop {
+        if fmin > -m {
+            fmin -= 1;
+            forward[ix(fmin - 1)] = -1;
+        } else {
+            fmin += 1;
+        }
+        if fmax < n {
+            fmax += 1;
+            forward[ix(fmax + 1)] = -1;
+        } else {
+            fmax -= 1;
+        }
+
+        for k in (fmin..=fmax).rev().step_by(2) {
+            let mut x = if forward[ix(k - 1)] >= forward[ix(k + 1)] {
+                forward[ix(k - 1)] + 1
+            } else {
+                forward[ix(k + 1)]
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[ix(k)] = x;
+
+            if odd && bmin <= k && k <= bmax && backward[ix(k)] <= x {
+                return (x as usize, y as usize);
+            }
+        }
+
+        if bmin > -m {
+            bmin -= 1;
+            backward[ix(bmin - 1)] = isize::MAX;
+        } else {
+            bmin += 1;
+        }
+        if bmax < n {
+            bmax += 1;
+            backward[ix(bmax + 1)] = isize::MAX;
+        } else {
+            bmax -= 1;
+        }
+
+        for k in (bmin..=bmax).rev().step_by(2) {
+            let mut x = if backward[ix(k - 1)] < backward[ix(k + 1)] {
+                backward[ix(k - 1)]
+            } else {
+                backward[ix(k + 1)] - 1
+            };
+            let mut y = x - k;
+            while x > 0 && y > 0 && old[(x - 1) as usize] == new[(y - 1) as usize] {
+                x -= 1;
+                y -= 1;
+            }
+            backward[ix(k)] = x;
+
+            if !odd && fmin <= k && k <= fmax && forward[ix(k)] >= x {
+                return (x as usize, y as usize);
+            }
+        }
+    }
+}
+
+// Formats a patch for a single file in unified format with a few lines of context around the
+// changes. Returns an empty string if contents are equal.
+pub(crate) fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    // Positions in both files before every edit, used to number hunks.
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for e in edits.iter() {
+        positions.push((old_pos, new_pos));
+        match e {
+            Edit::Equal(..) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete(_) => old_pos += 1,
+            Edit::Insert(_) => new_pos += 1,
+        }
+    }
+
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+
+    let mut i = 0;
+    while i < changed.len() {
+        // Changes separated by less than two contexts are joined in a single hunk.
+        let first = changed[i];
+        let mut last = first;
+        while i + 1 < changed.len() && changed[i + 1] - last <= 2 * CONTEXT_LINES + 1 {
+            i += 1;
+            last = changed[i];
+        }
+        i += 1;
+
+        let start = first.saturating_sub(CONTEXT_LINES);
+        let end = (last + CONTEXT_LINES + 1).min(edits.len());
+
+        let hunk = &edits[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+
+        for e in hunk {
+            match e {
+                Edit::Equal(o, _) => out.push_str(&format!(" {}\n", old_lines[*o])),
+                Edit::Delete(o) => out.push_str(&format!("-{}\n", old_lines[*o])),
+                Edit::Insert(n) => out.push_str(&format!("+{}\n", new_lines[*n])),
+            }
+        }
+    }
+
+    out
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // Empty range points to the line before the change.
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &[&str], new: &[&str], edits: &[Edit]) -> Vec<String> {
+        edits
+            .iter()
+            .filter_map(|e| match e {
+                Edit::Equal(o, _) => Some(old[*o].to_string()),
+                Edit::Insert(n) => Some(new[*n].to_string()),
+                Edit::Delete(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = vec!["a", "b", "c", "a", "b", "b", "a"];
+        let new = vec!["c", "b", "a", "b", "a", "c"];
+        let edits = diff_lines(&old, &new);
+
+        assert!(apply(&old, &new, &edits) == new);
+        // Known shortest edit script length for this pair.
+        let changes = edits
+            .iter()
+            .filter(|e| !matches!(e, Edit::Equal(..)))
+            .count();
+        assert!(changes == 5);
+
+        assert!(diff_lines(&[], &[]).is_empty());
+        assert!(diff_lines(&["a"], &[]) == vec![Edit::Delete(0)]);
+        assert!(diff_lines(&[], &["a"]) == vec![Edit::Insert(0)]);
+    }
+
+    #[test]
+    fn test_diff_lines_is_shortest() {
+        // Longest common subsequence length, which the shortest script keeps as equal lines.
+        fn lcs(old: &[&str], new: &[&str]) -> usize {
+            let mut row = vec![0; new.len() + 1];
+            for o in old {
+                let mut diagonal = 0;
+                for (j, n) in new.iter().enumerate() {
+                    let above = row[j + 1];
+                    row[j + 1] = if o == n {
+                        diagonal + 1
+                    } else {
+                        above.max(row[j])
+                    };
+                    diagonal = above;
+                }
+            }
+            row[new.len()]
+        }
+
+        // Small alphabet makes lots of partial matches, a fixed seed keeps the cases stable.
+        let mut seed: u32 = 42;
+        let mut lines = |len: usize| -> Vec<&str> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                    ["a", "b", "c"][(seed >> 16) as usize % 3]
+                })
+                .collect()
+        };
+
+        for i in 0..300 {
+            let old = lines(i % 13);
+            let new = lines(i % 7 + i % 5);
+            let edits = diff_lines(&old, &new);
+
+            assert!(apply(&old, &new, &edits) == new);
+            let equal = edits
+                .iter()
+                .filter(|e| matches!(e, Edit::Equal(..)))
+                .count();
+            assert!(equal == lcs(&old, &new));
+        }
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n17\n";
+
+        let patch = unified_diff("a/f", "b/f", old, new);
+        let expected = "--- a/f\n+++ b/f\n\
+            @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+            @@ -14,3 +14,4 @@\n 14\n 15\n 16\n+17\n";
+        assert!(patch == expected);
+
+        assert!(unified_diff("a/f", "b/f", old, old).is_empty());
+        assert!(
+            unified_diff("/dev/null", "b/f", "", "x\n")
+                == "--- /dev/null\n+++ b/f\n@@ -0,0 +1 @@\n+x\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let old = BTreeMap::from([
+            (PathBuf::from("kept"), String::from("1")),
+            (PathBuf::from("modified"), String::from("2")),
+            (PathBuf::from("removed"), String::from("3")),
+        ]);
+        let new = BTreeMap::from([
+            (PathBuf::from("added"), String::from("4")),
+            (PathBuf::from("kept"), String::from("1")),
+            (PathBuf::from("modified"), String::from("5")),
+        ]);
+
+        let changes: Vec<(ChangeKind, PathBuf)> = diff_files(&old, &new)
+            .into_iter()
+            .map(|c| (c.kind, c.path))
+            .collect();
+
+        assert!(
+            changes
+                == vec![
+                    (ChangeKind::Added, PathBuf::from("added")),
+                    (ChangeKind::Modified, PathBuf::from("modified")),
+                    (ChangeKind::Deleted, PathBuf::from("removed")),
+                ]
+        );
+    }
+}
@@ -1,11 +1,10 @@
 use std::io::Error as IoError;
+use std::path::PathBuf;
+
 use thiserror::Error as ThisError;
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error("life is full of surprises")]
-    Unexpected,
-
     #[error("current directory doesn't contain get repository")]
     NotAGetRepo,
 
@@ -21,6 +20,117 @@ pub enum Error {
     #[error("no such commit")]
     CommitNotFound,
 
+    #[error("there are no commits yet")]
+    NoCommitsYet,
+
+    #[error("current commit has no parent")]
+    NoParentCommit,
+
+    #[error("working directory has uncommitted changes")]
+    UncommittedChanges,
+
+    #[error("{0} is in progress, continue or abort it first")]
+    MergeInProgress(&'static str),
+
+    #[error("there is no merge, cherry-pick or revert in progress")]
+    NoMergeInProgress,
+
+    #[error("there is no rebase in progress")]
+    NoRebaseInProgress,
+
+    #[error("there is no bisect in progress")]
+    NoBisectInProgress,
+
+    #[error("bad commit {0} is an ancestor of a good one, are the marks swapped?")]
+    BisectBadIsAncestorOfGood(String),
+
+    #[error("bisect run stopped, {0}")]
+    BisectRunFailed(String),
+
+    #[error("there are no local changes to save")]
+    NoLocalChanges,
+
+    #[error("no stash entry stash@{{{0}}} found")]
+    StashNotFound(usize),
+
+    #[error("conflicts are not resolved in: {}", .0.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", "))]
+    UnresolvedConflicts(Vec<PathBuf>),
+
+    #[error("no file {} in commit {1}", .0.display())]
+    FileNotFound(PathBuf, String),
+
+    #[error("{0} is not a valid date, use YYYY-MM-DD [HH:MM[:SS]] or a unix timestamp")]
+    InvalidDate(String),
+
+    #[error("invalid config {}:{line}:{column}: {message}", path.display())]
+    ConfigError {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("{0} is not a valid identity, use `name <email>` or `name`")]
+    InvalidIdentity(String),
+
+    #[error("unknown config key {0}, known keys are author and ignore")]
+    UnknownConfigKey(String),
+
+    #[error("invalid value for config key {0}")]
+    InvalidConfigValue(String),
+
+    #[error("can't find the global config location, neither XDG_CONFIG_HOME nor HOME is set")]
+    NoGlobalConfig,
+
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("no object {0} found")]
+    ObjectNotFound(String),
+
+    #[error("object {0} is not a tree or a commit")]
+    NotATree(String),
+
+    #[error("{kind} {digest} is corrupt: {reason}")]
+    CorruptObject {
+        digest: String,
+        kind: &'static str,
+        reason: String,
+    },
+
+    #[error("invalid tree entry {0:?}, expected `type\\tdigest\\tname`")]
+    InvalidTreeEntry(String),
+
+    #[error("path {} is not valid utf-8", .0.display())]
+    PathNotUtf8(PathBuf),
+
+    #[error("{} is not a regular file or a directory, symlinks are not supported", .0.display())]
+    UnsupportedFileType(PathBuf),
+
+    #[error("{} is not a file", .0.display())]
+    NotAFile(PathBuf),
+
+    #[error("invalid reflog entry {0:?}")]
+    InvalidReflogEntry(String),
+
+    #[error("time before the unix epoch is not supported")]
+    TimeBeforeEpoch,
+
+    #[error("tag {0} already exists")]
+    TagAlreadyExists(String),
+
+    #[error("no tag {0} found")]
+    TagNotFound(String),
+
+    #[error("{0} is not a valid name")]
+    InvalidRefName(String),
+
+    #[error("unknown revision {0}")]
+    RevisionNotFound(String),
+
+    #[error("revision {0} is ambiguous, candidates are: {}", .1.join(", "))]
+    AmbiguousRevision(String, Vec<String>),
+
     #[error("repo working diractory not set before use")]
     WorkingDirNotSet,
 }
@@ -0,0 +1,231 @@
+use crate::date::parse_date;
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::show::CommitDetails;
+use crate::Repo;
+
+use std::collections::{BinaryHeap, HashSet};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+/// Filters for the commit history.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Only commits which changed a file or anything inside a directory.
+    pub path: Option<PathBuf>,
+    /// Keep following a file by its previous name when it was renamed without changes.
+    pub follow: bool,
+    /// Regular expression to look for in commit messages.
+    pub grep: Option<String>,
+    /// Regular expression to look for in commit authors.
+    pub author: Option<String>,
+    /// Only commits made at or after this date.
+    pub since: Option<String>,
+    /// Only commits made at or before this date.
+    pub until: Option<String>,
+}
+
+// Log options checked and parsed before the walk starts.
+#[derive(Debug, Clone)]
+struct Filters {
+    path: Option<PathBuf>,
+    follow: bool,
+    grep: Option<Regex>,
+    author: Option<Regex>,
+    since: Option<u64>,
+    until: Option<u64>,
+}
+
+/// Commits reachable from a starting one, the most recent first. Commits are read one at a time
+/// while iterating, so the history is never loaded at once.
+pub struct Log<'a> {
+    repo: &'a Repo,
+    filters: Filters,
+    // Commits to visit ordered by committer timestamp, then by digest.
+    queue: BinaryHeap<(u64, String)>,
+    seen: HashSet<String>,
+}
+
+impl Repo {
+    /// Walks the history starting from a commit. See `LogOptions` for filtering. Fails right
+    /// away if a pattern or a date can't be parsed.
+    pub fn log(&self, digest: &str, options: LogOptions) -> Result<Log<'_>, Error> {
+        let regex = |pattern: Option<String>| {
+            pattern
+                .map(|p| Regex::new(p.as_str()))
+                .transpose()
+                .map_err(|e| Error::InvalidPattern(e.to_string()))
+        };
+        let date = |date: Option<String>| {
+            date.map(|d| parse_date(d.as_str()).ok_or(Error::InvalidDate(d)))
+                .transpose()
+        };
+
+        let filters = Filters {
+            // Working directory root contains every change, so it filters nothing out.
+            path: options
+                .path
+                .map(|p| self.relative_path(&p))
+                .filter(|p| !p.as_os_str().is_empty()),
+            follow: options.follow,
+            grep: regex(options.grep)?,
+            author: regex(options.author)?,
+            since: date(options.since)?,
+            until: date(options.until)?,
+        };
+
+        let mut log = Log {
+            repo: self,
+            filters,
+            queue: BinaryHeap::new(),
+            seen: HashSet::new(),
+        };
+        log.push(digest.to_owned());
+
+        Ok(log)
+    }
+
+    // Digest of a tree or a blob at the given path inside a commit, None if there is nothing.
+    fn path_digest(&self, commit: &Object, path: &Path) -> Result<Option<String>, Error> {
+        Ok(self.path_entry(commit, path)?.map(|(_, digest)| digest))
+    }
+
+    // Previous name of a file added in a commit with the same content as one deleted from its
+    // parent.
+    fn renamed_from(
+        &self,
+        commit: &str,
+        parent: &str,
+        path: &Path,
+    ) -> Result<Option<PathBuf>, Error> {
+        let files = self.commit_files(commit)?;
+        let Some(digest) = files.get(path) else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .commit_files(parent)?
+            .into_iter()
+            .find(|(p, d)| d == digest && !files.contains_key(p))
+            .map(|(p, _)| p))
+    }
+}
+
+impl Log<'_> {
+    fn push(&mut self, digest: String) {
+        if !self.seen.insert(digest.clone()) {
+            return;
+        }
+
+        // Amended, picked and rebased commits are placed by the time they were made, not
+        // authored. Unreadable commits are reported when they are reached.
+        let timestamp = self
+            .repo
+            .read_commit_object(digest.clone())
+            .and_then(|commit| commit.committer().map(|(_, timestamp)| timestamp))
+            .unwrap_or(u64::MAX);
+        self.queue.push((timestamp, digest));
+    }
+
+    fn matches(&self, details: &CommitDetails) -> bool {
+        let f = &self.filters;
+
+        f.grep.as_ref().is_none_or(|r| r.is_match(&details.message))
+            && f.author
+                .as_ref()
+                .is_none_or(|r| r.is_match(&details.author))
+            && f.since.is_none_or(|s| details.timestamp >= s)
+            && f.until.is_none_or(|u| details.timestamp <= u)
+    }
+
+    // Whether a commit changed the followed path relative to all its parents, so merges which
+    // took the path from one side are left out.
+    fn touches_path(&mut self, commit: &Object, digest: &str) -> Result<bool, Error> {
+        let Some(path) = self.filters.path.clone() else {
+            return Ok(true);
+        };
+
+        let ours = self.repo.path_digest(commit, &path)?;
+        let parents = commit.parents();
+
+        if parents.is_empty() {
+            return Ok(ours.is_some());
+        }
+
+        for parent in parents.iter() {
+            let parent_commit = self.repo.read_commit_object(parent.to_string())?;
+            if self.repo.path_digest(&parent_commit, &path)? == ours {
+                return Ok(false);
+            }
+        }
+
+        if self.filters.follow && ours.is_some() {
+            if let Some(old_path) = self.repo.renamed_from(digest, parents[0], &path)? {
+                self.filters.path = Some(old_path);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Iterator for Log<'_> {
+    type Item = Result<CommitDetails, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((_, digest)) = self.queue.pop() {
+            let result = self
+                .repo
+                .read_commit_object(digest.clone())
+                .and_then(|commit| {
+                    for parent in commit.parents() {
+                        self.push(parent.to_owned());
+                    }
+
+                    let details = details(digest.as_str(), &commit)?;
+                    // Path is checked for every commit, so renames are followed even through
+                    // the commits filtered out.
+                    let touches_path = self.touches_path(&commit, digest.as_str())?;
+                    if !touches_path || !self.matches(&details) {
+                        return Ok(None);
+                    }
+
+                    Ok(Some(details))
+                });
+
+            match result {
+                Ok(Some(details)) => return Some(Ok(details)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        None
+    }
+}
+
+// Commit metadata without the changes, which are not needed for a history listing.
+fn details(digest: &str, commit: &Object) -> Result<CommitDetails, Error> {
+    let Object::Commit {
+        message, timestamp, ..
+    } = commit
+    else {
+        return Err(not_a(paths::COMMITS_DIR, digest));
+    };
+
+    let (committer, commit_timestamp) = commit.committer()?;
+
+    Ok(CommitDetails {
+        digest: digest.to_owned(),
+        parents: commit.parents().iter().map(|p| p.to_string()).collect(),
+        author: commit.author()?.to_owned(),
+        timestamp: timestamp.as_secs(),
+        committer: committer.to_owned(),
+        commit_timestamp,
+        message: message.clone(),
+        changes: Vec::new(),
+        patch: None,
+    })
+}
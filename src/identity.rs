@@ -0,0 +1,78 @@
+use crate::error::Error;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Person authoring or committing changes, written as `name <email>` or just `name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+impl Identity {
+    pub fn new(name: &str, email: Option<&str>) -> Result<Identity, Error> {
+        let identity = Identity {
+            name: name.trim().to_owned(),
+            email: email.map(|e| e.trim().to_owned()),
+        };
+
+        // Identities are stored on a single line of a commit, so angle brackets and line breaks
+        // would make them unreadable.
+        let invalid = |s: &str| s.is_empty() || s.contains(['<', '>', '\n', '\r']);
+        if invalid(&identity.name) || identity.email.as_deref().is_some_and(invalid) {
+            return Err(Error::InvalidIdentity(identity.to_string()));
+        }
+
+        Ok(identity)
+    }
+}
+
+impl FromStr for Identity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Identity, Error> {
+        let invalid = || Error::InvalidIdentity(s.to_owned());
+
+        match s.trim().split_once('<') {
+            Some((name, rest)) => {
+                let email = rest.strip_suffix('>').ok_or_else(invalid)?;
+                Identity::new(name, Some(email)).map_err(|_| invalid())
+            }
+            None => Identity::new(s, None).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.email.as_ref() {
+            Some(email) => write!(f, "{} <{}>", self.name, email),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_identity() {
+        let identity: Identity = "Jane Doe <jane@example.com>".parse().unwrap();
+        assert!(identity.name == "Jane Doe");
+        assert!(identity.email.as_deref() == Some("jane@example.com"));
+        assert!(identity.to_string() == "Jane Doe <jane@example.com>");
+
+        let identity: Identity = "  Vitalii Shvedchenko ".parse().unwrap();
+        assert!(identity.name == "Vitalii Shvedchenko");
+        assert!(identity.email.is_none());
+
+        assert!("".parse::<Identity>().is_err());
+        assert!("<jane@example.com>".parse::<Identity>().is_err());
+        assert!("Jane <jane@example.com".parse::<Identity>().is_err());
+        assert!("Jane <>".parse::<Identity>().is_err());
+        assert!("Jane <a> <b>".parse::<Identity>().is_err());
+        assert!("Jane\nDoe".parse::<Identity>().is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use crate::error::Error;
+use crate::{Repo, DEFAULT_IGNORE};
+
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Name of per-directory ignore files.
+const IGNORE_FILE: &str = ".getignore";
+
+// Single gitignore-style rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    glob: String,
+    // Pattern starting with `!` re-includes paths excluded by the previous patterns.
+    negated: bool,
+    // Pattern ending with `/` matches only directories.
+    dir_only: bool,
+    // Pattern with a slash at the beginning or in the middle matches paths from the base, others
+    // match file names at any level.
+    anchored: bool,
+    // Directory the pattern applies to, relative to the root with slashes. Empty for the root.
+    base: String,
+    // Where the pattern comes from, None for the built-in ones.
+    source: Option<PathBuf>,
+    // Numbered from one.
+    line: usize,
+    // Pattern as written.
+    text: String,
+}
+
+/// Ignore rules in gitignore format. Supports `*`, `?`, `[...]` and `**` wildcards, patterns
+/// anchored to the base directory with a slash, directory-only patterns with a trailing slash
+/// and negation with a leading `!`. The last matching pattern decides.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+/// Pattern which made a path ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreMatch {
+    /// Ignore file, config file or environment variable the pattern comes from, None for
+    /// built-in patterns. Files inside the repo are relative to the root.
+    pub source: Option<PathBuf>,
+    /// Line of the ignore file or position in the config `ignore` list, numbered from one.
+    pub line: usize,
+    pub pattern: String,
+}
+
+impl IgnoreRules {
+    // Parses root patterns without a source skipping empty lines and comments.
+    pub(crate) fn new<'a>(lines: impl IntoIterator<Item = &'a str>) -> IgnoreRules {
+        let mut rules = IgnoreRules::default();
+        rules.extend(lines, "", None);
+
+        rules
+    }
+
+    // Adds patterns applied to the subtree of the base directory.
+    pub(crate) fn extend<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = &'a str>,
+        base: &str,
+        source: Option<&Path>,
+    ) {
+        self.patterns.extend(
+            lines
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, l)| parse(l).map(|p| (i, p)))
+                .map(|(i, p)| Pattern {
+                    base: base.to_owned(),
+                    source: source.map(|s| s.to_owned()),
+                    line: i + 1,
+                    ..p
+                }),
+        );
+    }
+
+    /// Whether a path relative to the root is ignored, either by itself or because one of its
+    /// parent directories is.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.explain(path, is_dir).is_some()
+    }
+
+    /// Pattern which makes a path relative to the root ignored, None if it is not ignored.
+    pub(crate) fn explain(&self, path: &Path, is_dir: bool) -> Option<IgnoreMatch> {
+        let parts: Vec<&str> = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => s.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        (1..=parts.len()).find_map(|i| {
+            let is_last = i == parts.len();
+            self.last_match(&parts[..i].join("/"), parts[i - 1], !is_last || is_dir)
+                .filter(|p| !p.negated)
+                .map(|p| IgnoreMatch {
+                    source: p.source.clone(),
+                    line: p.line,
+                    pattern: p.text.clone(),
+                })
+        })
+    }
+
+    // Pattern deciding for the path itself not looking at its parents.
+    fn last_match(&self, path: &str, name: &str, is_dir: bool) -> Option<&Pattern> {
+        self.patterns.iter().rev().find(|p| {
+            if p.dir_only && !is_dir {
+                return false;
+            }
+
+            let relative = if p.base.is_empty() {
+                Some(path)
+            } else {
+                path.strip_prefix(p.base.as_str())
+                    .and_then(|r| r.strip_prefix('/'))
+            };
+
+            match relative {
+                Some(r) if p.anchored => glob_match(p.glob.as_bytes(), r.as_bytes()),
+                Some(_) => glob_match(p.glob.as_bytes(), name.as_bytes()),
+                None => false,
+            }
+        })
+    }
+}
+
+impl Repo {
+    // Rules from the config layers, then `.getignore` files from the root down, so deeper files take
+    // precedence. Files in ignored directories are not read. Repo's own files are always ignored,
+    // so they go last.
+    pub(crate) fn ignore_rules(&self) -> Result<IgnoreRules, Error> {
+        let mut rules = IgnoreRules::default();
+        for list in self.config.ignore.iter() {
+            let source = PathBuf::from(list.source.to_string());
+            rules.extend(
+                list.patterns.iter().map(|s| s.as_str()),
+                "",
+                Some(source.as_path()),
+            );
+        }
+
+        let defaults = IgnoreRules::new(DEFAULT_IGNORE.iter().copied());
+        self.collect_ignore_files(Path::new(""), &mut rules, &defaults)?;
+
+        rules.patterns.extend(defaults.patterns);
+
+        Ok(rules)
+    }
+
+    /// Pattern which makes a path ignored, None if it is not ignored. Path is either absolute or
+    /// relative to the working directory root.
+    pub fn check_ignore(&self, path: &Path) -> Result<Option<IgnoreMatch>, Error> {
+        let is_dir = self.work_dir.join(path).is_dir();
+        let path = self.relative_path(path);
+
+        Ok(self.ignore_rules()?.explain(&path, is_dir))
+    }
+
+    fn collect_ignore_files(
+        &self,
+        dir: &Path,
+        rules: &mut IgnoreRules,
+        defaults: &IgnoreRules,
+    ) -> Result<(), Error> {
+        let full_dir = self.work_dir.join(dir);
+
+        let ignore_file = full_dir.join(IGNORE_FILE);
+        if ignore_file.is_file() {
+            let content = fs::read_to_string(&ignore_file)?;
+            let base = dir
+                .to_str()
+                .ok_or_else(|| Error::PathNotUtf8(dir.to_owned()))?;
+            rules.extend(content.lines(), base, Some(&dir.join(IGNORE_FILE)));
+        }
+
+        for entry in fs::read_dir(&full_dir)? {
+            let e = entry?;
+            if !e.file_type()?.is_dir() {
+                continue;
+            }
+
+            let path = dir.join(e.file_name());
+            if rules.is_ignored(&path, true) || defaults.is_ignored(&path, true) {
+                continue;
+            }
+            self.collect_ignore_files(&path, rules, defaults)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for IgnoreMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source.as_ref() {
+            Some(source) => write!(f, "{}:{}:{}", source.display(), self.line, self.pattern),
+            None => write!(f, "<built-in>:{}:{}", self.line, self.pattern),
+        }
+    }
+}
+
+fn parse(line: &str) -> Option<Pattern> {
+    let text = line.trim_end();
+    if text.is_empty() || text.starts_with('#') {
+        return None;
+    }
+
+    let (negated, line) = match text.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('\\').unwrap_or(text)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    let anchored = line.contains('/');
+    let glob = line.strip_prefix('/').unwrap_or(line);
+    if glob.is_empty() {
+        return None;
+    }
+
+    Some(Pattern {
+        glob: glob.to_owned(),
+        negated,
+        dir_only,
+        anchored,
+        base: String::new(),
+        source: None,
+        line: 0,
+        text: text.to_owned(),
+    })
+}
+
+// Matches a path against a glob. Wildcards don't cross slashes except `**`, which matches any
+// number of directories.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*'] => true,
+        [b'*', b'*', b'/', rest @ ..] => {
+            // Zero or more leading directories.
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, c)| *c == b'/' && glob_match(rest, &text[i + 1..]))
+        }
+        [b'*', rest @ ..] => {
+            let rest = if rest.first() == Some(&b'*') {
+                &rest[1..]
+            } else {
+                rest
+            };
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        [b'?', rest @ ..] => matches!(text, [c, tail @ ..] if *c != b'/' && glob_match(rest, tail)),
+        [b'[', rest @ ..] => match (text, match_class(rest, text.first().copied())) {
+            ([_, tail @ ..], Some((true, after))) => glob_match(after, tail),
+            _ => false,
+        },
+        [b'\\', c, rest @ ..] | [c, rest @ ..] => {
+            matches!(text, [t, tail @ ..] if t == c && glob_match(rest, tail))
+        }
+    }
+}
+
+// Matches a character against a class like `[a-z]` or `[!0-9]` given the pattern right after
+// the opening bracket. Returns whether it matched and the pattern after the class, None if the
+// class is not closed.
+fn match_class(pattern: &[u8], c: Option<u8>) -> Option<(bool, &[u8])> {
+    let c = c.filter(|c| *c != b'/');
+
+    let (negated, mut rest) = match pattern {
+        [b'!' | b'^', rest @ ..] => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match rest {
+            [] => return None,
+            [b']', tail @ ..] if !first => {
+                return Some((c.is_some() && matched != negated, tail));
+            }
+            [lo, b'-', hi, tail @ ..] if *hi != b']' => {
+                matched |= c.is_some_and(|c| *lo <= c && c <= *hi);
+                rest = tail;
+            }
+            [x, tail @ ..] => {
+                matched |= c == Some(*x);
+                rest = tail;
+            }
+        }
+        first = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.log", b"debug.log"));
+        assert!(!glob_match(b"*.log", b"logs/debug.txt"));
+        assert!(!glob_match(b"*.log", b"logs/debug.log"));
+        assert!(glob_match(b"file?.txt", b"file1.txt"));
+        assert!(glob_match(b"[a-c]at", b"bat"));
+        assert!(!glob_match(b"[!a-c]at", b"bat"));
+        assert!(glob_match(b"docs/**/*.tmp", b"docs/a.tmp"));
+        assert!(glob_match(b"docs/**/*.tmp", b"docs/a/b/c.tmp"));
+        assert!(!glob_match(b"docs/**/*.tmp", b"src/docs/a.tmp"));
+        assert!(glob_match(b"**/build", b"a/b/build"));
+        assert!(glob_match(b"build/**", b"build/a/b"));
+        assert!(glob_match(b"\\*literal", b"*literal"));
+        assert!(!glob_match(b"\\*literal", b"aliteral"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let rules = IgnoreRules::new([
+            "# comment",
+            "",
+            "*.log",
+            "!keep.log",
+            "/build",
+            "target/",
+            "docs/**/*.tmp",
+        ]);
+
+        assert!(rules.is_ignored(Path::new("debug.log"), false));
+        assert!(rules.is_ignored(Path::new("src/debug.log"), false));
+        assert!(!rules.is_ignored(Path::new("src/keep.log"), false));
+
+        assert!(rules.is_ignored(Path::new("build"), true));
+        assert!(rules.is_ignored(Path::new("build/out.o"), false));
+        assert!(!rules.is_ignored(Path::new("src/build"), true));
+
+        assert!(rules.is_ignored(Path::new("target"), true));
+        assert!(rules.is_ignored(Path::new("src/target/out.o"), false));
+        assert!(!rules.is_ignored(Path::new("target"), false));
+
+        assert!(rules.is_ignored(Path::new("docs/a/b.tmp"), false));
+        assert!(!rules.is_ignored(Path::new("docs/a/b.md"), false));
+        assert!(!rules.is_ignored(Path::new("# comment"), false));
+    }
+
+    #[test]
+    fn test_nested_rules() {
+        let mut rules = IgnoreRules::new(["*.log"]);
+        rules.extend(
+            ["/out", "!keep.log"],
+            "sub",
+            Some(Path::new("sub/.getignore")),
+        );
+
+        assert!(rules.is_ignored(Path::new("sub/out/a.txt"), false));
+        assert!(!rules.is_ignored(Path::new("out/a.txt"), false));
+        assert!(!rules.is_ignored(Path::new("sub/deeper/out"), true));
+        assert!(!rules.is_ignored(Path::new("sub/keep.log"), false));
+        assert!(rules.is_ignored(Path::new("keep.log"), false));
+        assert!(!rules.is_ignored(Path::new("subway/out"), true));
+
+        let explained = rules.explain(Path::new("sub/out/a.txt"), false);
+        assert!(
+            explained
+                == Some(IgnoreMatch {
+                    source: Some(PathBuf::from("sub/.getignore")),
+                    line: 1,
+                    pattern: String::from("/out"),
+                })
+        );
+    }
+}
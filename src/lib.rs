@@ -1,39 +1,64 @@
+mod bisect;
+mod blame;
+mod clean;
+mod config;
+mod date;
+mod diff;
 pub mod error;
+mod history;
+mod identity;
+mod ignore;
+mod merge;
 mod object;
 mod paths;
+mod pick;
+mod plumbing;
+mod rebase;
+mod reflog;
+mod revision;
+mod show;
+mod stash;
+mod status;
+mod tag;
 mod worktree;
 
+pub use crate::bisect::BisectOutcome;
+pub use crate::blame::BlameLine;
+pub use crate::clean::CleanOptions;
+pub use crate::config::{
+    config_entries, config_get, config_set, config_unset, ConfigEntry, ConfigScope, ConfigSource,
+    ConfigValue,
+};
+pub use crate::diff::{Change, ChangeKind};
+pub use crate::history::{Log, LogOptions};
+pub use crate::identity::Identity;
+pub use crate::ignore::IgnoreMatch;
+pub use crate::merge::MergeOutcome;
+pub use crate::plumbing::{ObjectKind, TreeEntry};
+pub use crate::rebase::RebaseOutcome;
+pub use crate::reflog::LogEntry;
+pub use crate::show::CommitDetails;
+pub use crate::stash::StashEntry;
+pub use crate::tag::{Tag, TagAnnotation};
+
+use crate::config::Config;
 use crate::error::Error;
 use crate::worktree::RepoWithState;
 
-use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::path::PathBuf;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use log::warn;
-use serde::Deserialize;
-use toml;
-use users::get_current_username;
+use serde::Serialize;
 
 const DEFAULT_FILE_PERMISSIONS: u32 = 0o644;
 const DEFAULT_DIR_PERMISSIONS: u32 = 0o755;
 const EMPTY_REF: &str = "0000000000000000000000000000000000000000";
 const DEFAULT_IGNORE: &[&str] = &[".get", ".get.toml"]; // Default ignore patterns.
 
-#[derive(Debug, Clone, Deserialize)]
-pub(crate) struct Config {
-    #[serde(default)]
-    ignore: Vec<String>,
-    #[serde(default = "default_author")]
-    author: String,
-    // #[serde(default)]
-    // remotes: Vec<String>, // TODO Use some URL kind of type.
-}
-
 #[derive(Debug, Clone)]
 pub struct Repo {
     work_dir: PathBuf,
@@ -41,6 +66,18 @@ pub struct Repo {
     head: String,
 }
 
+/// A commit saved and checked out as HEAD.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitResult {
+    pub commit: String,
+}
+
+/// A commit whose files were restored, HEAD points to it now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RestoreResult {
+    pub commit: String,
+}
+
 impl Repo {
     // TODO Rework it to actually take &Path instead of mut PathBuf.
     pub fn init(cur_dir: &mut PathBuf) -> Result<Repo, Error> {
@@ -49,7 +86,7 @@ impl Repo {
         create_utility_dirs(cur_dir)?;
         create_utility_files(cur_dir)?;
 
-        let config = resolve_config(cur_dir.as_path())?;
+        let config = Config::resolve(cur_dir.as_path())?;
 
         Ok(Repo {
             work_dir: cur_dir.clone(),
@@ -58,9 +95,9 @@ impl Repo {
         })
     }
 
-    pub fn try_from(cur_dir: &PathBuf) -> Result<Repo, Error> {
+    pub fn try_from(cur_dir: &Path) -> Result<Repo, Error> {
         let work_dir = paths::repo_dir(cur_dir)?;
-        let config = resolve_config(cur_dir.as_path())?;
+        let config = Config::resolve(work_dir.as_path())?;
         let head = read_head(work_dir.as_path())?;
 
         Ok(Repo {
@@ -71,48 +108,197 @@ impl Repo {
     }
 
     pub fn commit(&self, msg: Option<&str>, now: SystemTime) -> Result<String, Error> {
+        Ok(self.commit_as(msg, None, now)?.commit)
+    }
+
+    /// Commits on behalf of another author, the configured identity stays the committer.
+    pub fn commit_as(
+        &self,
+        msg: Option<&str>,
+        author: Option<&Identity>,
+        now: SystemTime,
+    ) -> Result<CommitResult, Error> {
         // TODO Change default message to smthg more informative.
         let message = msg.unwrap_or("default commit message");
+        let head = read_head(self.work_dir.as_path())?;
+
+        // Commit after resolving merge conflicts concludes the merge.
+        if self.read_merge_state()?.is_some() {
+            let commit = self.merge_continue_as(msg, author, now)?;
+            return Ok(CommitResult { commit });
+        }
+
+        // Stopped rebase has its own way to commit the resolved changes.
+        if self.rebase_dir_path().is_dir() {
+            return Err(Error::MergeInProgress("rebase"));
+        }
+
+        let author = author.map(|a| (a.to_string(), now));
+        let commit = self.commit_on(head, message, "commit", author, now)?;
+
+        Ok(CommitResult { commit })
+    }
 
-        let repo_with_state = RepoWithState::from_files(self.clone(), message, now)?;
-        let new_commit_digest = repo_with_state.save_commit().map(|s| s.to_string())?;
-        self.write_head(new_commit_digest.as_str())?;
+    /// Replaces the current commit with a new one made from the working directory files. New
+    /// commit gets the parent of the replaced one and keeps its message unless a new one is given.
+    pub fn amend(&self, msg: Option<&str>, now: SystemTime) -> Result<String, Error> {
+        Ok(self.amend_as(msg, None, now)?.commit)
+    }
+
+    /// Amends the current commit. It keeps its original author and the time it was authored
+    /// unless another author is given.
+    pub fn amend_as(
+        &self,
+        msg: Option<&str>,
+        author: Option<&Identity>,
+        now: SystemTime,
+    ) -> Result<CommitResult, Error> {
+        // Files stopped on conflicts have markers in them and the operation state would be lost.
+        self.check_no_merge_state()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        let commit = self.read_commit_object(head.clone())?;
+        // First commit in the repo keeps the empty ref as its parent.
+        let parent = commit.parent()?.to_owned();
+        let message = match (msg, &commit) {
+            (Some(m), _) => m.to_owned(),
+            (None, object::Object::Commit { message, .. }) => message.clone(),
+            _ => return Err(object::not_a(paths::COMMITS_DIR, head.as_str())),
+        };
+        let author = match author {
+            Some(a) => (a.to_string(), now),
+            None => self.authorship(head.as_str())?,
+        };
+
+        let commit = self.commit_on(
+            parent,
+            message.as_str(),
+            "commit (amend)",
+            Some(author),
+            now,
+        )?;
+
+        Ok(CommitResult { commit })
+    }
+
+    // Commits working directory files on top of the parents and moves HEAD there. Author and
+    // the time of authoring are the committer ones unless given.
+    fn commit_on(
+        &self,
+        parent: String,
+        message: &str,
+        operation: &str,
+        author: Option<(String, SystemTime)>,
+        now: SystemTime,
+    ) -> Result<String, Error> {
+        let (author, authored) = author.unwrap_or_else(|| (self.config.author.to_string(), now));
+        let new_commit_digest =
+            self.save_commit_as(parent, author.as_str(), authored, message, now)?;
+        self.move_head(new_commit_digest.as_str(), operation, message, now)?;
 
         Ok(new_commit_digest)
     }
 
-    pub fn restore(&self, digest: &str) -> Result<(), Error> {
+    // Saves working directory files as a commit with the given parents, author and time of
+    // authoring without moving HEAD.
+    fn save_commit_as(
+        &self,
+        parent: String,
+        author: &str,
+        authored: SystemTime,
+        message: &str,
+        now: SystemTime,
+    ) -> Result<String, Error> {
+        let mut repo = self.clone();
+        repo.head = parent;
+
+        let repo_with_state = RepoWithState::from_files(repo, message, author, authored, now)?;
+        let digest = repo_with_state.save_commit()?;
+
+        Ok(digest.to_owned())
+    }
+
+    // Author of a commit with the time it was authored, to keep them when the commit is
+    // recreated.
+    fn authorship(&self, digest: &str) -> Result<(String, SystemTime), Error> {
+        let commit = self.read_commit_object(digest.to_owned())?;
+        let author = commit.author()?.to_owned();
+        let object::Object::Commit { timestamp, .. } = commit else {
+            return Err(object::not_a(paths::COMMITS_DIR, digest));
+        };
+
+        Ok((author, UNIX_EPOCH + timestamp))
+    }
+
+    pub fn restore(&self, digest: &str) -> Result<RestoreResult, Error> {
+        self.checkout_files(digest)?;
+
+        let message = format!("moving to {digest}");
+        self.move_head(digest, "restore", message.as_str(), SystemTime::now())?;
+
+        Ok(RestoreResult {
+            commit: digest.to_owned(),
+        })
+    }
+
+    /// Moves HEAD back to the parent of the current commit. Working directory is left untouched
+    /// unless `hard` is set, in which case the parent commit files are restored. The undone
+    /// commit is not deleted and can still be found in the reflog.
+    pub fn uncommit(&self, hard: bool, now: SystemTime) -> Result<String, Error> {
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoParentCommit);
+        }
+
+        let commit = self.read_commit_object(head.clone())?;
+        let parent = commit
+            .parents()
+            .first()
+            .ok_or(Error::NoParentCommit)?
+            .to_string();
+
+        if hard {
+            self.checkout_files(parent.as_str())?;
+        }
+
+        let message = format!("undo {head}");
+        self.move_head(parent.as_str(), "uncommit", message.as_str(), now)?;
+
+        Ok(parent)
+    }
+
+    // Replaces working directory files with the ones from the given commit.
+    fn checkout_files(&self, digest: &str) -> Result<(), Error> {
         // Check the commit exists before cleaning the directory.
         let _ = self.read_commit_object(digest.to_owned())?;
 
-        worktree::clean_before_restore(self.work_dir.as_path(), &self)?;
+        worktree::clean_before_restore(self.work_dir.as_path(), self, &self.ignore_rules()?)?;
         let repo_with_state = RepoWithState::from_commit(self.clone(), digest.to_owned())?;
         repo_with_state.restore_files()?;
-        self.write_head(digest)?;
 
         Ok(())
     }
 
-    fn write_head(&self, digest: &str) -> Result<(), Error> {
+    // Points HEAD to a new commit and records the movement in the log.
+    fn move_head(
+        &self,
+        digest: &str,
+        operation: &str,
+        message: &str,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        let old = read_head(self.work_dir.as_path())?;
         fs::write(paths::head_path(self.work_dir.as_ref()), digest)?;
+        self.append_log(old.as_str(), digest, operation, message, now)?;
 
         Ok(())
     }
 }
 
-fn resolve_config(work_dir: &Path) -> Result<Config, Error> {
-    let config: Config;
-
-    if let Ok(cfg_file) = fs::read_to_string(work_dir.join(".get.toml")) {
-        config = toml::from_str(cfg_file.as_ref()).map_err(|_| Error::Unexpected)?;
-    } else {
-        warn!("could not read config file, default is set");
-        config = default_config();
-    }
-
-    Ok(config)
-}
-
 fn read_head(base_path: &Path) -> Result<String, Error> {
     let str = fs::read_to_string(paths::head_path(base_path))?;
 
@@ -141,16 +327,30 @@ fn create_utility_dirs(cur_path: &mut PathBuf) -> Result<(), Error> {
     create_dir(cur_path)?;
     cur_path.pop();
 
+    cur_path.push(paths::TAG_DIR);
+    create_dir(cur_path)?;
+    cur_path.pop();
+
+    cur_path.pop();
+
+    // Crete `.get/refs` and `.get/refs/tags`.
+    cur_path.push(paths::REFS_DIR);
+    create_dir(cur_path)?;
+
+    cur_path.push(paths::TAGS_DIR);
+    create_dir(cur_path)?;
+    cur_path.pop();
+
     cur_path.pop();
     cur_path.pop();
 
     Ok(())
 }
 
-fn create_dir(cur_path: &mut PathBuf) -> io::Result<()> {
-    fs::create_dir(cur_path.as_path())?;
+fn create_dir(cur_path: &Path) -> io::Result<()> {
+    fs::create_dir(cur_path)?;
     fs::set_permissions(
-        cur_path.as_path(),
+        cur_path,
         fs::Permissions::from_mode(DEFAULT_DIR_PERMISSIONS),
     )?;
 
@@ -179,20 +379,3 @@ fn create_utility_files(cur_path: &mut PathBuf) -> io::Result<()> {
 
     Ok(())
 }
-
-fn default_config() -> Config {
-    Config {
-        ignore: vec![],
-        author: default_author(),
-    }
-}
-
-fn default_author() -> String {
-    get_current_username()
-        .unwrap_or_else(|| {
-            warn!("couldn't fetch user name, default user name used instead");
-            OsString::from("unknown author")
-        })
-        .into_string()
-        .unwrap_or(String::from("unknown author"))
-}
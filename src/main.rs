@@ -1,11 +1,28 @@
 use get::error::Error;
+use get::{BisectOutcome, MergeOutcome, RebaseOutcome};
 
 use std::env;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::SystemTime;
 
-use clap::{arg, Command};
+use clap::{arg, Arg, ArgGroup, ArgMatches, Command};
 use log::{error, info};
+use serde::Serialize;
+use serde_json::json;
+
+// Exit codes for errors scripts may want to tell from a failed command. They are stable, new
+// ones only get added.
+const CONFIG_ERROR_EXIT_CODE: i32 = 3;
+const CORRUPT_OBJECT_EXIT_CODE: i32 = 4;
+const INVALID_TREE_ENTRY_EXIT_CODE: i32 = 5;
+const PATH_NOT_UTF8_EXIT_CODE: i32 = 6;
+const INVALID_REFLOG_ENTRY_EXIT_CODE: i32 = 7;
+const UNSUPPORTED_FILE_TYPE_EXIT_CODE: i32 = 8;
+
+// Set by the global `--json` flag, errors are printed as json documents as well then.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     env_logger::builder()
@@ -17,74 +34,802 @@ fn main() {
         .author("Vitalii Shvedchenko <vitaly.shvedchenko@gmail.com>")
         .about("Like git but worse")
         .subcommand_required(true)
+        .arg(
+            arg!(--json "print results of commit, restore, status, show, log and errors as json")
+                .global(true),
+        )
         .subcommand(Command::new("init").about("creates new repo in currenct directory"))
         .subcommand(
             Command::new("commit")
                 .about("saves the changes")
-                .arg(arg!([message] "optional message")),
+                .arg(arg!([message] "optional message"))
+                .arg(arg!(--amend "replace the last commit, keeping its message if none given"))
+                .arg(arg!(--author <AUTHOR> "commit on behalf of someone else, `name <email>`")),
         )
         .subcommand(
             Command::new("restore")
                 .about("resotres saved files")
-                .arg(arg!([digest] "commit digest to restore").required(true)),
+                .arg(arg!([revision] "commit to restore").required(true)),
+        )
+        .subcommand(
+            Command::new("uncommit")
+                .about("moves HEAD back to the parent of the last commit")
+                .arg(arg!(--hard "also restore the parent commit files")),
+        )
+        .subcommand(Command::new("status").about("shows files changed since HEAD"))
+        .subcommand(
+            Command::new("clean")
+                .about("removes files which are not in HEAD and not ignored")
+                .arg(arg!(dry_run: -n "only show what would be removed"))
+                .arg(arg!(
+                    directories: -d "also remove untracked and empty directories, ignored files in them are kept"
+                ))
+                .arg(arg!(ignored: -x "also remove ignored files")),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("merges another commit into HEAD")
+                .arg(
+                    arg!([revision] "commit to merge")
+                        .required_unless_present_any(["continue", "abort"]),
+                )
+                .arg(arg!(--continue "commit the merge once conflicts are resolved"))
+                .arg(arg!(--abort "drop the merge and restore files as they were before it"))
+                .group(ArgGroup::new("action").args(["revision", "continue", "abort"])),
+        )
+        .subcommand(
+            Command::new("cherry-pick")
+                .about("applies changes made by a commit on top of HEAD")
+                .arg(
+                    arg!([revision] "commit to pick")
+                        .required_unless_present_any(["continue", "abort"]),
+                )
+                .arg(arg!(--continue "commit the changes once conflicts are resolved"))
+                .arg(arg!(--abort "drop the changes and restore files as they were before"))
+                .group(ArgGroup::new("action").args(["revision", "continue", "abort"])),
+        )
+        .subcommand(
+            Command::new("revert")
+                .about("undoes changes made by a commit with a new commit on top of HEAD")
+                .arg(
+                    arg!([revision] "commit to revert")
+                        .required_unless_present_any(["continue", "abort"]),
+                )
+                .arg(arg!(--continue "commit the changes once conflicts are resolved"))
+                .arg(arg!(--abort "drop the changes and restore files as they were before"))
+                .group(ArgGroup::new("action").args(["revision", "continue", "abort"])),
+        )
+        .subcommand(
+            Command::new("rebase")
+                .about("replays HEAD commits on top of another commit")
+                .arg(
+                    arg!([upstream] "commit to replay onto")
+                        .required_unless_present_any(["continue", "skip", "abort"]),
+                )
+                .arg(arg!(--continue "commit resolved conflicts and replay the rest"))
+                .arg(arg!(--skip "drop the conflicting commit and replay the rest"))
+                .arg(arg!(--abort "drop the rebase and go back to the original HEAD"))
+                .group(ArgGroup::new("action").args(["upstream", "continue", "skip", "abort"])),
+        )
+        .subcommand(
+            Command::new("stash")
+                .about("saves uncommitted changes aside and brings them back later")
+                .subcommand(
+                    Command::new("push")
+                        .about("saves changes and restores HEAD files")
+                        .arg(arg!([message] "optional message")),
+                )
+                .subcommand(Command::new("list").about("lists saved changes"))
+                .subcommand(
+                    Command::new("apply")
+                        .about("applies saved changes keeping them in the list")
+                        .arg(stash_index_arg()),
+                )
+                .subcommand(
+                    Command::new("pop")
+                        .about("applies saved changes and removes them from the list")
+                        .arg(stash_index_arg()),
+                )
+                .subcommand(
+                    Command::new("drop")
+                        .about("removes saved changes from the list")
+                        .arg(stash_index_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("bisect")
+                .about("finds the commit which introduced a regression with a binary search")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("start")
+                        .about("starts a search, optionally with known bad and good commits")
+                        .arg(arg!([bad] "commit with the regression"))
+                        .arg(arg!([good] ... "commits without the regression")),
+                )
+                .subcommand(
+                    Command::new("good")
+                        .about("marks a commit without the regression")
+                        .arg(arg!([revision] "commit to mark, HEAD by default")),
+                )
+                .subcommand(
+                    Command::new("bad")
+                        .about("marks a commit with the regression")
+                        .arg(arg!([revision] "commit to mark, HEAD by default")),
+                )
+                .subcommand(
+                    Command::new("skip")
+                        .about("marks a commit which can't be tested")
+                        .arg(arg!([revision] "commit to mark, HEAD by default")),
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("marks commits by the exit code of a command")
+                        .arg(
+                            arg!(<command> ... "command with its arguments")
+                                .trailing_var_arg(true)
+                                .allow_hyphen_values(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("reset").about("ends the search and restores the original HEAD"),
+                ),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("shows commit details and changed files")
+                .arg(arg!([revision] "commit to show").required(true))
+                .arg(arg!(-p --patch "also print the full patch")),
+        )
+        .subcommand(
+            Command::new("blame")
+                .about("shows the commit which last changed every line of a file")
+                .arg(arg!([path] "file to blame").required(true))
+                .arg(arg!([revision] "commit to start from, HEAD by default")),
+        )
+        .subcommand(
+            Command::new("tag")
+                .about("lists, creates or deletes tags")
+                .arg(arg!([name] "tag name, lists all tags if omitted"))
+                .arg(arg!([revision] "commit to tag, HEAD by default"))
+                .arg(arg!(-m --message <MESSAGE> "make an annotated tag with a message"))
+                .arg(
+                    arg!(-d --delete "delete the tag")
+                        .requires("name")
+                        .conflicts_with_all(["revision", "message"]),
+                ),
+        )
+        .subcommand(
+            Command::new("rev-parse")
+                .about("resolves a revision to a commit digest")
+                .arg(arg!([revision] "revision expression").required(true)),
+        )
+        .subcommand(
+            Command::new("log")
+                .about("shows commit history")
+                .arg(arg!([revision] "commit to start from, HEAD by default"))
+                .arg(arg!([path] "only commits changing this file or directory").last(true))
+                .arg(arg!(--follow "follow the file through renames").requires("path"))
+                .arg(arg!(--grep <REGEX> "only commits with messages matching the pattern"))
+                .arg(arg!(--author <REGEX> "only commits with authors matching the pattern"))
+                .arg(arg!(--since <DATE> "only commits made at or after the date"))
+                .arg(arg!(--until <DATE> "only commits made at or before the date")),
+        )
+        .subcommand(Command::new("reflog").about("shows the history of HEAD movements"))
+        .subcommand(
+            Command::new("cat-file")
+                .about("prints an object type or content")
+                .arg(arg!(kind: -t "print object type"))
+                .arg(arg!(pretty: -p "print object content"))
+                .group(
+                    ArgGroup::new("mode")
+                        .args(["kind", "pretty"])
+                        .required(true),
+                )
+                .arg(arg!([digest] "object digest").required(true)),
+        )
+        .subcommand(
+            Command::new("ls-tree")
+                .about("lists a tree or a commit root tree")
+                .arg(arg!(recursive: -r "recurse into subtrees"))
+                .arg(arg!([digest] "tree digest or commit revision").required(true)),
+        )
+        .subcommand(
+            Command::new("hash-object")
+                .about("calculates a file digest the way commit does")
+                .arg(arg!(write: -w "save the object into the storage"))
+                .arg(arg!([file] "file to hash").required(true)),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("reads and writes settings")
+                .subcommand_required(true)
+                .arg(arg!(--global "use the global config instead of the repo one").global(true))
+                .subcommand(
+                    Command::new("get")
+                        .about("prints a value in effect")
+                        .arg(arg!(<key> "setting name")),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("sets a value, a list of patterns for ignore")
+                        .arg(arg!(<key> "setting name"))
+                        .arg(arg!(<value> ... "new value")),
+                )
+                .subcommand(
+                    Command::new("unset")
+                        .about("removes a value")
+                        .arg(arg!(<key> "setting name")),
+                )
+                .subcommand(
+                    Command::new("list").about("lists values in effect with where they come from"),
+                ),
+        )
+        .subcommand(
+            Command::new("check-ignore")
+                .about("prints the paths which are ignored, exits with 1 if none is")
+                .arg(arg!(verbose: -v "also print the file, line and pattern ignoring a path"))
+                .arg(arg!(<path> ... "paths to check")),
         )
         .get_matches();
 
-    let mut cur_dir = env::current_dir().unwrap_or_else(|e| {
-        error!("{}", Error::IoError(e));
-        exit(1);
-    });
+    let json_output = matches.get_flag("json");
+    JSON_OUTPUT.store(json_output, Ordering::Relaxed);
+
+    let mut cur_dir = env::current_dir().unwrap_or_else(|e| fail(Error::IoError(e)));
 
     match matches.subcommand() {
-        Some(("init", _)) => match get::Repo::init(&mut cur_dir) {
-            Ok(_) => {
-                info!("Repo created!");
-            }
-            Err(err) => {
-                error!("{err}");
-                exit(1);
-            }
-        },
+        Some(("init", _)) => {
+            get::Repo::init(&mut cur_dir).unwrap_or_else(|err| fail(err));
+            info!("Repo created!");
+        }
         Some(("commit", sub_matches)) => {
             let msg = sub_matches.get_one::<String>("message");
             let sys_time = SystemTime::now();
-            let repo = match get::Repo::try_from(&cur_dir) {
-                Ok(repo) => repo,
-                Err(err) => {
-                    error!("{err}");
-                    exit(1);
+            let author = sub_matches
+                .get_one::<String>("author")
+                .map(|a| a.parse::<get::Identity>().unwrap_or_else(|err| fail(err)));
+            let repo = open_repo(&cur_dir);
+            let result = if sub_matches.get_flag("amend") {
+                repo.amend_as(msg.map(|s| s.as_str()), author.as_ref(), sys_time)
+            } else {
+                repo.commit_as(msg.map(|s| s.as_str()), author.as_ref(), sys_time)
+            }
+            .unwrap_or_else(|err| fail(err));
+            if json_output {
+                print_json(&result);
+            } else {
+                info!("Commit {} saved successfully.", result.commit);
+            }
+        }
+        Some(("restore", sub_matches)) => {
+            // We unwrap here safely since revision is explicitly required by clap.
+            let rev = sub_matches.get_one::<String>("revision").unwrap();
+            let repo = open_repo(&cur_dir);
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let result = repo
+                .restore(digest.as_str())
+                .unwrap_or_else(|err| fail(err));
+            if json_output {
+                print_json(&result);
+            } else {
+                info!("Commit {} restored successfully.", result.commit);
+            }
+        }
+        Some(("uncommit", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+            let parent = repo
+                .uncommit(sub_matches.get_flag("hard"), SystemTime::now())
+                .unwrap_or_else(|err| fail(err));
+            info!("HEAD moved back to {}.", parent);
+        }
+        Some(("status", _)) => {
+            let repo = open_repo(&cur_dir);
+            let changes = repo.status().unwrap_or_else(|err| fail(err));
+            if json_output {
+                print_json(&changes);
+                return;
+            }
+            for c in changes {
+                println!("{}\t{}", c.kind, c.path.display());
+            }
+        }
+        Some(("clean", sub_matches)) => {
+            let options = get::CleanOptions {
+                dry_run: sub_matches.get_flag("dry_run"),
+                directories: sub_matches.get_flag("directories"),
+                ignored: sub_matches.get_flag("ignored"),
+            };
+            let repo = open_repo(&cur_dir);
+            let removed = repo.clean(options).unwrap_or_else(|err| fail(err));
+            let action = if options.dry_run {
+                "Would remove"
+            } else {
+                "Removing"
+            };
+            for path in removed {
+                println!("{action} {}", path.display());
+            }
+        }
+        Some(("merge", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+
+            if sub_matches.get_flag("continue") {
+                let commit = repo
+                    .merge_continue(None, SystemTime::now())
+                    .unwrap_or_else(|err| fail(err));
+                info!("Merge commit {} saved successfully.", commit);
+                return;
+            }
+
+            if sub_matches.get_flag("abort") {
+                repo.merge_abort().unwrap_or_else(|err| fail(err));
+                info!("Merge aborted.");
+                return;
+            }
+
+            // We unwrap here safely since revision is required without other flags.
+            let rev = sub_matches.get_one::<String>("revision").unwrap();
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let outcome = repo
+                .merge(digest.as_str(), SystemTime::now())
+                .unwrap_or_else(|err| fail(err));
+            match outcome {
+                MergeOutcome::UpToDate => info!("Already up to date."),
+                MergeOutcome::FastForward(commit) => info!("Fast-forwarded to {}.", commit),
+                MergeOutcome::Merged { commit, .. } => {
+                    info!("Merge commit {} saved successfully.", commit)
                 }
+                MergeOutcome::Conflicts { conflicts, .. } => report_conflicts(&conflicts, "merge"),
+            }
+        }
+        Some((command @ ("cherry-pick" | "revert"), sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+
+            if sub_matches.get_flag("continue") {
+                let commit = repo
+                    .merge_continue(None, SystemTime::now())
+                    .unwrap_or_else(|err| fail(err));
+                info!("Commit {} saved successfully.", commit);
+                return;
+            }
+
+            if sub_matches.get_flag("abort") {
+                repo.merge_abort().unwrap_or_else(|err| fail(err));
+                info!("{} aborted.", command);
+                return;
+            }
+
+            // We unwrap here safely since revision is required without other flags.
+            let rev = sub_matches.get_one::<String>("revision").unwrap();
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let outcome = if command == "revert" {
+                repo.revert(digest.as_str(), SystemTime::now())
+            } else {
+                repo.cherry_pick(digest.as_str(), SystemTime::now())
+            }
+            .unwrap_or_else(|err| fail(err));
+            match outcome {
+                MergeOutcome::Conflicts { conflicts, .. } => report_conflicts(&conflicts, command),
+                MergeOutcome::Merged { commit, .. } => {
+                    info!("Commit {} saved successfully.", commit)
+                }
+                _ => unreachable!("get: {command} always makes a commit or stops on conflicts"),
+            }
+        }
+        Some(("rebase", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+            let now = SystemTime::now();
+
+            if sub_matches.get_flag("abort") {
+                let head = repo.rebase_abort(now).unwrap_or_else(|err| fail(err));
+                info!("Rebase aborted, HEAD is back at {}.", head);
+                return;
+            }
+
+            let outcome = if sub_matches.get_flag("continue") {
+                repo.rebase_continue(now)
+            } else if sub_matches.get_flag("skip") {
+                repo.rebase_skip(now)
+            } else {
+                // We unwrap here safely since upstream is required without other flags.
+                let rev = sub_matches.get_one::<String>("upstream").unwrap();
+                let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+                repo.rebase(digest.as_str(), now)
+            }
+            .unwrap_or_else(|err| fail(err));
+            match outcome {
+                RebaseOutcome::UpToDate => info!("Already up to date."),
+                RebaseOutcome::FastForward(commit) => info!("Fast-forwarded to {}.", commit),
+                RebaseOutcome::Rebased(commit) => info!("Rebased, HEAD is now at {}.", commit),
+                RebaseOutcome::Conflicts { commit, conflicts } => {
+                    error!("Could not apply {}.", commit);
+                    report_conflicts(&conflicts, "rebase")
+                }
+            }
+        }
+        Some(("stash", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+
+            match sub_matches.subcommand() {
+                Some(("list", _)) => {
+                    let entries = repo.stash_list().unwrap_or_else(|err| fail(err));
+                    for (i, e) in entries.iter().enumerate() {
+                        println!("stash@{{{}}}: {}", i, e.message);
+                    }
+                }
+                Some(("apply", stash_matches)) => {
+                    let index = stash_index(stash_matches);
+                    let conflicts = repo.stash_apply(index).unwrap_or_else(|err| fail(err));
+                    report_stash_conflicts(&conflicts);
+                    info!("stash@{{{}}} applied.", index);
+                }
+                Some(("pop", stash_matches)) => {
+                    let index = stash_index(stash_matches);
+                    let conflicts = repo.stash_pop(index).unwrap_or_else(|err| fail(err));
+                    report_stash_conflicts(&conflicts);
+                    info!("stash@{{{}}} applied and dropped.", index);
+                }
+                Some(("drop", stash_matches)) => {
+                    let index = stash_index(stash_matches);
+                    let entry = repo.stash_drop(index).unwrap_or_else(|err| fail(err));
+                    info!("Dropped stash@{{{}}} ({}).", index, entry.digest);
+                }
+                // Plain stash command saves the changes just like push.
+                _ => {
+                    let msg = sub_matches
+                        .subcommand_matches("push")
+                        .and_then(|m| m.get_one::<String>("message"));
+                    let entry = repo
+                        .stash_push(msg.map(|s| s.as_str()), SystemTime::now())
+                        .unwrap_or_else(|err| fail(err));
+                    info!("Saved stash {}: {}.", entry.digest, entry.message);
+                }
+            }
+        }
+        Some(("bisect", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+            let resolve = |m: &ArgMatches, name: &str| {
+                let rev = m.get_one::<String>(name).map_or("HEAD", |s| s.as_str());
+                repo.resolve_rev(rev).unwrap_or_else(|err| fail(err))
             };
-            match repo.commit(msg.map(|s| s.as_str()), sys_time) {
-                Ok(commit_digest) => {
-                    info!("Commit {} saved successfully.", commit_digest);
+
+            let outcome = match sub_matches.subcommand() {
+                Some(("start", bisect_matches)) => {
+                    let bad = bisect_matches
+                        .get_one::<String>("bad")
+                        .map(|_| resolve(bisect_matches, "bad"));
+                    let good: Vec<String> = bisect_matches
+                        .get_many::<String>("good")
+                        .unwrap_or_default()
+                        .map(|rev| repo.resolve_rev(rev).unwrap_or_else(|err| fail(err)))
+                        .collect();
+                    let good: Vec<&str> = good.iter().map(|g| g.as_str()).collect();
+                    repo.bisect_start(bad.as_deref(), &good)
+                }
+                Some(("good", bisect_matches)) => {
+                    repo.bisect_good(resolve(bisect_matches, "revision").as_str())
+                }
+                Some(("bad", bisect_matches)) => {
+                    repo.bisect_bad(resolve(bisect_matches, "revision").as_str())
+                }
+                Some(("skip", bisect_matches)) => {
+                    repo.bisect_skip(resolve(bisect_matches, "revision").as_str())
+                }
+                Some(("run", bisect_matches)) => {
+                    let command: Vec<String> = bisect_matches
+                        .get_many::<String>("command")
+                        .unwrap_or_default()
+                        .cloned()
+                        .collect();
+                    repo.bisect_run(&command)
+                }
+                Some(("reset", _)) => {
+                    let head = repo.bisect_reset().unwrap_or_else(|err| fail(err));
+                    info!("Bisect is over, HEAD is back at {}.", head);
+                    return;
+                }
+                _ => unreachable!("get: unknown bisect subcommand"),
+            }
+            .unwrap_or_else(|err| fail(err));
+
+            match outcome {
+                BisectOutcome::Waiting => info!("Waiting for both good and bad commits."),
+                BisectOutcome::Testing { commit, remaining } => {
+                    info!("Testing {}, {} commits left to test.", commit, remaining)
                 }
-                Err(err) => {
-                    error!("{err}");
-                    exit(1);
+                BisectOutcome::Found(commit) => println!("{commit} is the first bad commit"),
+                BisectOutcome::Inconclusive(commits) => {
+                    for commit in commits {
+                        println!("{commit}");
+                    }
+                    info!("The first bad commit could be any of these, the rest were skipped.");
                 }
             }
         }
-        Some(("restore", sub_matches)) => {
+        Some(("show", sub_matches)) => {
+            // We unwrap here safely since revision is explicitly required by clap.
+            let rev = sub_matches.get_one::<String>("revision").unwrap();
+            let with_patch = sub_matches.get_flag("patch");
+            let repo = open_repo(&cur_dir);
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let details = repo
+                .show(digest.as_str(), with_patch)
+                .unwrap_or_else(|err| fail(err));
+            if json_output {
+                print_json(&details);
+            } else {
+                print!("{details}");
+            }
+        }
+        Some(("blame", sub_matches)) => {
+            // We unwrap here safely since path is explicitly required by clap.
+            let path = PathBuf::from(sub_matches.get_one::<String>("path").unwrap());
+            let rev = sub_matches
+                .get_one::<String>("revision")
+                .map_or("HEAD", |s| s.as_str());
+            let repo = open_repo(&cur_dir);
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let lines = repo
+                .blame(&cur_dir.join(path), digest.as_str())
+                .unwrap_or_else(|err| fail(err));
+            for line in lines {
+                println!("{line}");
+            }
+        }
+        Some(("tag", sub_matches)) => {
+            let repo = open_repo(&cur_dir);
+            match sub_matches.get_one::<String>("name") {
+                None => {
+                    for tag in repo.tags().unwrap_or_else(|err| fail(err)) {
+                        println!("{}", tag.name);
+                    }
+                }
+                Some(name) if sub_matches.get_flag("delete") => {
+                    let tag = repo.delete_tag(name).unwrap_or_else(|err| fail(err));
+                    info!("Tag {} ({}) deleted.", tag.name, tag.target);
+                }
+                Some(name) => {
+                    let rev = sub_matches
+                        .get_one::<String>("revision")
+                        .map_or("HEAD", |s| s.as_str());
+                    let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+                    let message = sub_matches.get_one::<String>("message");
+                    let tag = repo
+                        .create_tag(
+                            name,
+                            digest.as_str(),
+                            message.map(|s| s.as_str()),
+                            SystemTime::now(),
+                        )
+                        .unwrap_or_else(|err| fail(err));
+                    info!("Tag {} created for commit {}.", tag.name, tag.target);
+                }
+            }
+        }
+        Some(("rev-parse", sub_matches)) => {
+            // We unwrap here safely since revision is explicitly required by clap.
+            let rev = sub_matches.get_one::<String>("revision").unwrap();
+            let repo = open_repo(&cur_dir);
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            println!("{digest}");
+        }
+        Some(("log", sub_matches)) => {
+            let rev = sub_matches
+                .get_one::<String>("revision")
+                .map_or("HEAD", |s| s.as_str());
+            let options = get::LogOptions {
+                path: sub_matches
+                    .get_one::<String>("path")
+                    .map(|p| cur_dir.join(p)),
+                follow: sub_matches.get_flag("follow"),
+                grep: sub_matches.get_one::<String>("grep").cloned(),
+                author: sub_matches.get_one::<String>("author").cloned(),
+                since: sub_matches.get_one::<String>("since").cloned(),
+                until: sub_matches.get_one::<String>("until").cloned(),
+            };
+            let repo = open_repo(&cur_dir);
+            let digest = repo.resolve_rev(rev).unwrap_or_else(|err| fail(err));
+            let log = repo
+                .log(digest.as_str(), options)
+                .unwrap_or_else(|err| fail(err));
+            if json_output {
+                let log: Vec<get::CommitDetails> = log
+                    .collect::<Result<_, _>>()
+                    .unwrap_or_else(|err| fail(err));
+                print_json(&log);
+                return;
+            }
+            for (i, details) in log.enumerate() {
+                let details = details.unwrap_or_else(|err| fail(err));
+                if i > 0 {
+                    println!();
+                }
+                print!("{details}");
+            }
+        }
+        Some(("reflog", _)) => {
+            let repo = open_repo(&cur_dir);
+            let entries = repo.reflog().unwrap_or_else(|err| fail(err));
+            for (i, e) in entries.iter().enumerate() {
+                println!("{} HEAD@{{{}}}: {}: {}", e.new, i, e.operation, e.message);
+            }
+        }
+        Some(("cat-file", sub_matches)) => {
             // We unwrap here safely since digest is explicitly required by clap.
             let digest = sub_matches.get_one::<String>("digest").unwrap();
-            let repo = match get::Repo::try_from(&cur_dir) {
-                Ok(repo) => repo,
-                Err(err) => {
-                    error!("{err}");
-                    exit(1);
-                }
+            let repo = open_repo(&cur_dir);
+            if sub_matches.get_flag("kind") {
+                let kind = repo.object_kind(digest).unwrap_or_else(|err| fail(err));
+                println!("{kind}");
+            } else {
+                let content = repo.cat_object(digest).unwrap_or_else(|err| fail(err));
+                println!("{content}");
+            }
+        }
+        Some(("ls-tree", sub_matches)) => {
+            // We unwrap here safely since digest is explicitly required by clap.
+            let digest = sub_matches.get_one::<String>("digest").unwrap();
+            let repo = open_repo(&cur_dir);
+            // Trees are not reachable by revisions, so fall back to a plain digest.
+            let digest = match repo.resolve_rev(digest) {
+                Ok(commit) => commit,
+                Err(Error::RevisionNotFound(_)) => digest.to_owned(),
+                Err(err) => fail(err),
             };
-            match repo.restore(digest.as_str()) {
-                Ok(_) => {
-                    info!("Commit {} restored successfully.", digest);
+            let entries = repo
+                .ls_tree(&digest, sub_matches.get_flag("recursive"))
+                .unwrap_or_else(|err| fail(err));
+            for e in entries {
+                println!("{e}");
+            }
+        }
+        Some(("hash-object", sub_matches)) => {
+            // We unwrap here safely since file is explicitly required by clap.
+            let file = PathBuf::from(sub_matches.get_one::<String>("file").unwrap());
+            let repo = open_repo(&cur_dir);
+            let digest = repo
+                .hash_object(&cur_dir.join(file), sub_matches.get_flag("write"))
+                .unwrap_or_else(|err| fail(err));
+            println!("{digest}");
+        }
+        Some(("config", sub_matches)) => {
+            let scope = sub_matches
+                .get_flag("global")
+                .then_some(get::ConfigScope::Global);
+            match sub_matches.subcommand() {
+                Some(("get", config_matches)) => {
+                    // We unwrap here safely since key is explicitly required by clap.
+                    let key = config_matches.get_one::<String>("key").unwrap();
+                    let entries =
+                        get::config_get(&cur_dir, scope, key).unwrap_or_else(|err| fail(err));
+                    if entries.is_empty() {
+                        exit(1);
+                    }
+                    for e in entries {
+                        match e.value {
+                            get::ConfigValue::String(s) => println!("{s}"),
+                            get::ConfigValue::List(items) => {
+                                for item in items {
+                                    println!("{item}");
+                                }
+                            }
+                        }
+                    }
+                }
+                Some(("set", config_matches)) => {
+                    // We unwrap here safely since key and value are explicitly required by clap.
+                    let key = config_matches.get_one::<String>("key").unwrap();
+                    let values: Vec<String> = config_matches
+                        .get_many::<String>("value")
+                        .unwrap()
+                        .cloned()
+                        .collect();
+                    get::config_set(
+                        &cur_dir,
+                        scope.unwrap_or(get::ConfigScope::Repo),
+                        key,
+                        &values,
+                    )
+                    .unwrap_or_else(|err| fail(err));
                 }
-                Err(err) => {
-                    error!("{err}");
-                    exit(1);
+                Some(("unset", config_matches)) => {
+                    // We unwrap here safely since key is explicitly required by clap.
+                    let key = config_matches.get_one::<String>("key").unwrap();
+                    get::config_unset(&cur_dir, scope.unwrap_or(get::ConfigScope::Repo), key)
+                        .unwrap_or_else(|err| fail(err));
                 }
+                Some(("list", _)) => {
+                    let entries =
+                        get::config_entries(&cur_dir, scope).unwrap_or_else(|err| fail(err));
+                    for e in entries {
+                        println!("{}\t{} = {}", e.source, e.key, e.value);
+                    }
+                }
+                _ => unreachable!("get: unknown config subcommand"),
+            }
+        }
+        Some(("check-ignore", sub_matches)) => {
+            let verbose = sub_matches.get_flag("verbose");
+            let repo = open_repo(&cur_dir);
+            let mut any_ignored = false;
+            // We unwrap here safely since path is explicitly required by clap.
+            for path in sub_matches.get_many::<String>("path").unwrap() {
+                let found = repo
+                    .check_ignore(&cur_dir.join(path))
+                    .unwrap_or_else(|err| fail(err));
+                match found {
+                    Some(m) if verbose => println!("{m}\t{path}"),
+                    Some(_) => println!("{path}"),
+                    None => continue,
+                }
+                any_ignored = true;
+            }
+            if !any_ignored {
+                exit(1);
             }
         }
         _ => unreachable!("get: unknown subcommand"),
     }
 }
+
+fn open_repo(cur_dir: &Path) -> get::Repo {
+    get::Repo::try_from(cur_dir).unwrap_or_else(|err| fail(err))
+}
+
+fn stash_index_arg() -> Arg {
+    arg!([index] "stash entry index, the most recent one by default")
+        .value_parser(clap::value_parser!(usize))
+}
+
+fn stash_index(matches: &ArgMatches) -> usize {
+    matches.get_one::<usize>("index").copied().unwrap_or(0)
+}
+
+// Stash is kept when applying it conflicts, so there is nothing to continue or abort.
+fn report_stash_conflicts(conflicts: &[PathBuf]) {
+    if conflicts.is_empty() {
+        return;
+    }
+
+    for path in conflicts.iter() {
+        println!("conflict\t{}", path.display());
+    }
+    error!("There are conflicts, resolve them manually, the stash entry is kept.");
+    exit(1);
+}
+
+fn report_conflicts(conflicts: &[PathBuf], command: &str) -> ! {
+    for path in conflicts.iter() {
+        println!("conflict\t{}", path.display());
+    }
+    error!("There are conflicts, resolve them and run {command} --continue.");
+    exit(1);
+}
+
+// Prints a single line json document to stdout.
+fn print_json<T: Serialize>(value: &T) {
+    // Paths are the only thing which may fail to serialize.
+    let document =
+        serde_json::to_string(value).unwrap_or_else(|_| fail(Error::UnsupportedEncoding));
+    println!("{document}");
+}
+
+fn fail(err: Error) -> ! {
+    error!("{err}");
+
+    let code = match err {
+        Error::ConfigError { .. } => CONFIG_ERROR_EXIT_CODE,
+        Error::CorruptObject { .. } => CORRUPT_OBJECT_EXIT_CODE,
+        Error::InvalidTreeEntry(_) => INVALID_TREE_ENTRY_EXIT_CODE,
+        Error::PathNotUtf8(_) => PATH_NOT_UTF8_EXIT_CODE,
+        Error::InvalidReflogEntry(_) => INVALID_REFLOG_ENTRY_EXIT_CODE,
+        Error::UnsupportedFileType(_) => UNSUPPORTED_FILE_TYPE_EXIT_CODE,
+        _ => 1,
+    };
+
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        print_json(&json!({ "error": err.to_string(), "code": code }));
+    }
+
+    exit(code)
+}
@@ -0,0 +1,707 @@
+use crate::diff::{diff_lines, Edit};
+use crate::error::Error;
+use crate::{read_head, Identity, Repo, DEFAULT_FILE_PERMISSIONS, EMPTY_REF};
+
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const OURS_MARKER: &str = "<<<<<<<";
+const BASE_SEPARATOR: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>>";
+
+/// Result of merging another commit into HEAD or applying its changes with cherry-pick or revert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The commit is already reachable from HEAD, nothing to do.
+    UpToDate,
+    /// HEAD is an ancestor of the commit, so HEAD was simply moved to it.
+    FastForward(String),
+    /// Changes were merged into a new commit. Merge commit gets both parents.
+    Merged {
+        /// Best common ancestor of both commits, None for unrelated histories.
+        base: Option<String>,
+        commit: String,
+    },
+    /// Merge stopped to let conflicts be resolved, then it can be continued or aborted.
+    Conflicts {
+        /// Cherry-pick base is the parent of the picked commit and revert base is the reverted
+        /// commit itself.
+        base: Option<String>,
+        /// Paths with conflict markers written into them. A file changed on one side and deleted
+        /// on the other gets the changed version between markers.
+        conflicts: Vec<PathBuf>,
+    },
+}
+
+// Operations which share the three-way merge and may stop on conflicts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Merge,
+    CherryPick,
+    Revert,
+}
+
+// Merge stopped on conflicts. Stored in separate files under `.get` until the merge is
+// continued or aborted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MergeState {
+    pub operation: Operation,
+    // HEAD before the merge started.
+    pub orig_head: String,
+    // Commit being merged into HEAD, picked or reverted.
+    pub merge_head: String,
+    pub message: String,
+    pub conflicts: Vec<PathBuf>,
+}
+
+// State of a path after the merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Merged {
+    // Content already in the storage.
+    Blob(String),
+    // New content, possibly with conflict markers.
+    Text(String),
+}
+
+// Files of a merged snapshot with the paths which need manual resolution.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MergedFiles {
+    pub files: BTreeMap<PathBuf, Merged>,
+    pub conflicts: Vec<PathBuf>,
+}
+
+impl Repo {
+    /// Merges a commit into HEAD. Nothing is done if the commit is already merged and HEAD is
+    /// fast-forwarded if it has no commits of its own. Otherwise changes made on only one side
+    /// since the common ancestor are taken as is, files changed on both sides are merged line by
+    /// line and a merge commit with both parents is made. Conflicts are written into the files
+    /// with markers and the merge is stopped until it is continued or aborted.
+    pub fn merge(&self, digest: &str, now: SystemTime) -> Result<MergeOutcome, Error> {
+        self.check_no_merge_state()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        // Make sure it is a commit before doing anything.
+        let _ = self.read_commit_object(digest.to_owned())?;
+
+        let base = self.merge_base(head.as_str(), digest)?;
+
+        if base.as_deref() == Some(digest) {
+            return Ok(MergeOutcome::UpToDate);
+        }
+
+        self.check_clean()?;
+
+        if base.as_deref() == Some(head.as_str()) {
+            let files = self
+                .commit_files(digest)?
+                .into_iter()
+                .map(|(path, digest)| (path, Merged::Blob(digest)))
+                .collect();
+            self.write_merged(head.as_str(), &files)?;
+
+            let message = format!("fast-forward to {digest}");
+            self.move_head(digest, "merge", message.as_str(), now)?;
+
+            return Ok(MergeOutcome::FastForward(digest.to_owned()));
+        }
+
+        let merged = self.merge_commits(
+            base.as_deref().unwrap_or(EMPTY_REF),
+            head.as_str(),
+            digest,
+            ("HEAD", digest),
+        )?;
+        self.write_merged(head.as_str(), &merged.files)?;
+
+        let message = format!("Merge commit {digest}");
+
+        if !merged.conflicts.is_empty() {
+            self.write_merge_state(&MergeState {
+                operation: Operation::Merge,
+                orig_head: head,
+                merge_head: digest.to_owned(),
+                message,
+                conflicts: merged.conflicts.clone(),
+            })?;
+
+            return Ok(MergeOutcome::Conflicts {
+                base,
+                conflicts: merged.conflicts,
+            });
+        }
+
+        let parents = format!("{head} {digest}");
+        let commit = self.commit_on(parents, message.as_str(), "merge", None, now)?;
+
+        Ok(MergeOutcome::Merged { base, commit })
+    }
+
+    /// Concludes a merge, cherry-pick or revert stopped on conflicts with a new commit once all
+    /// the conflict markers are removed from the files. Saved message is used unless a new one
+    /// is given.
+    pub fn merge_continue(&self, msg: Option<&str>, now: SystemTime) -> Result<String, Error> {
+        self.merge_continue_as(msg, None, now)
+    }
+
+    /// Continues a merge, cherry-pick or revert on behalf of another author, who replaces the
+    /// one the operation would record.
+    pub fn merge_continue_as(
+        &self,
+        msg: Option<&str>,
+        author: Option<&Identity>,
+        now: SystemTime,
+    ) -> Result<String, Error> {
+        let state = self.read_merge_state()?.ok_or(Error::NoMergeInProgress)?;
+
+        let unresolved = self.unresolved_conflicts(&state.conflicts)?;
+        if !unresolved.is_empty() {
+            return Err(Error::UnresolvedConflicts(unresolved));
+        }
+
+        let head = read_head(self.work_dir.as_path())?;
+        let parents = match state.operation {
+            Operation::Merge => format!("{} {}", head, state.merge_head),
+            Operation::CherryPick | Operation::Revert => head,
+        };
+        let message = msg.unwrap_or(state.message.as_str());
+        let operation = format!("commit ({})", state.operation);
+        let author = match author {
+            Some(a) => Some((a.to_string(), now)),
+            None => self.operation_author(state.operation, state.merge_head.as_str())?,
+        };
+        let commit = self.commit_on(parents, message, operation.as_str(), author, now)?;
+
+        self.clear_merge_state()?;
+
+        Ok(commit)
+    }
+
+    /// Drops a merge, cherry-pick or revert stopped on conflicts and brings back the working
+    /// directory as it was before it.
+    pub fn merge_abort(&self) -> Result<(), Error> {
+        let state = self.read_merge_state()?.ok_or(Error::NoMergeInProgress)?;
+
+        self.checkout_files(state.orig_head.as_str())?;
+        self.clear_merge_state()?;
+
+        Ok(())
+    }
+
+    /// Finds the best common ancestor of two commits, that is a common ancestor which is not an
+    /// ancestor of any other common ancestor. None if histories are unrelated.
+    pub fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>, Error> {
+        let ours = self.ancestors(one)?;
+
+        // Walk the other history stopping at commits common with ours, so only the closest
+        // common ancestors are collected.
+        let mut candidates = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([other.to_owned()]);
+        while let Some(digest) = queue.pop_front() {
+            if !seen.insert(digest.clone()) {
+                continue;
+            }
+
+            if ours.contains(&digest) {
+                candidates.push(digest);
+                continue;
+            }
+
+            queue.extend(self.commit_parents(digest.as_str())?);
+        }
+
+        // In criss-cross histories one candidate can still be an ancestor of another one.
+        let mut redundant = HashSet::new();
+        for c in candidates.iter() {
+            for p in self.commit_parents(c.as_str())? {
+                redundant.extend(self.ancestors(p.as_str())?);
+            }
+        }
+
+        Ok(candidates.into_iter().find(|c| !redundant.contains(c)))
+    }
+
+    // Three-way merge of two commits against their base. Labels are put next to conflict markers.
+    pub(crate) fn merge_commits(
+        &self,
+        base: &str,
+        ours: &str,
+        theirs: &str,
+        labels: (&str, &str),
+    ) -> Result<MergedFiles, Error> {
+        let base_files = self.commit_files(base)?;
+        let our_files = self.commit_files(ours)?;
+        let their_files = self.commit_files(theirs)?;
+
+        let all_paths: BTreeSet<&PathBuf> = base_files
+            .keys()
+            .chain(our_files.keys())
+            .chain(their_files.keys())
+            .collect();
+
+        let mut merged = MergedFiles::default();
+
+        for path in all_paths {
+            let b = base_files.get(path);
+            let o = our_files.get(path);
+            let t = their_files.get(path);
+
+            let resolved = if o == t || t == b {
+                o.cloned()
+            } else if o == b {
+                t.cloned()
+            } else {
+                match (o, t) {
+                    (Some(o), Some(t)) => {
+                        let base_content = match b {
+                            Some(b) => self.read_blob_content(b)?,
+                            None => String::new(),
+                        };
+                        let (content, conflict) = merge_lines(
+                            base_content.as_str(),
+                            self.read_blob_content(o)?.as_str(),
+                            self.read_blob_content(t)?.as_str(),
+                            labels,
+                        );
+                        if conflict {
+                            merged.conflicts.push(path.clone());
+                        }
+                        merged.files.insert(path.clone(), Merged::Text(content));
+                        continue;
+                    }
+                    // Changed on one side and deleted on the other. Changed version goes between
+                    // markers against the empty deleted one, so the file is resolved either by
+                    // removing the markers or by removing the file.
+                    (Some(changed), None) | (None, Some(changed)) => {
+                        let content = self.read_blob_content(changed)?;
+                        let (ours, theirs) = match o {
+                            Some(_) => (content.as_str(), ""),
+                            None => ("", content.as_str()),
+                        };
+                        merged.conflicts.push(path.clone());
+                        merged.files.insert(
+                            path.clone(),
+                            Merged::Text(conflict_block(ours, theirs, labels)),
+                        );
+                        continue;
+                    }
+                    (None, None) => None,
+                }
+            };
+
+            if let Some(digest) = resolved {
+                merged.files.insert(path.clone(), Merged::Blob(digest));
+            }
+        }
+
+        Ok(merged)
+    }
+
+    // Brings working directory from the state of the given commit to the merged one touching
+    // only the files which differ.
+    pub(crate) fn write_merged(
+        &self,
+        from: &str,
+        files: &BTreeMap<PathBuf, Merged>,
+    ) -> Result<(), Error> {
+        let current = self.commit_files(from)?;
+
+        for path in current.keys().filter(|p| !files.contains_key(*p)) {
+            fs::remove_file(self.work_dir.join(path))?;
+            self.remove_empty_dirs(path)?;
+        }
+
+        for (path, merged) in files.iter() {
+            let content = match merged {
+                Merged::Blob(digest) if current.get(path) == Some(digest) => continue,
+                Merged::Blob(digest) => self.read_blob_content(digest)?,
+                Merged::Text(text) => text.clone(),
+            };
+
+            let full_path = self.work_dir.join(path);
+            if let Some(dir) = full_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&full_path, content)?;
+            fs::set_permissions(
+                full_path,
+                fs::Permissions::from_mode(DEFAULT_FILE_PERMISSIONS),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Fails if working directory differs from HEAD, since merging would overwrite the changes.
+    pub(crate) fn check_clean(&self) -> Result<(), Error> {
+        let head = read_head(self.work_dir.as_path())?;
+
+        if self.working_files()? != self.commit_files(head.as_str())? {
+            return Err(Error::UncommittedChanges);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn write_merge_state(&self, state: &MergeState) -> Result<(), Error> {
+        let conflicts: Vec<&str> = state
+            .conflicts
+            .iter()
+            .map(|p| p.to_str().ok_or(Error::UnsupportedEncoding))
+            .collect::<Result<_, _>>()?;
+
+        fs::write(self.orig_head_path(), state.orig_head.as_str())?;
+        fs::write(self.merge_msg_path(), state.message.as_str())?;
+        fs::write(self.merge_conflicts_path(), conflicts.join("\n"))?;
+        // Head file goes last since its presence marks the operation in progress.
+        fs::write(
+            self.operation_head_path(state.operation),
+            state.merge_head.as_str(),
+        )?;
+
+        Ok(())
+    }
+
+    pub(crate) fn read_merge_state(&self) -> Result<Option<MergeState>, Error> {
+        let Some(operation) = [Operation::Merge, Operation::CherryPick, Operation::Revert]
+            .into_iter()
+            .find(|o| self.operation_head_path(*o).is_file())
+        else {
+            return Ok(None);
+        };
+
+        let conflicts = fs::read_to_string(self.merge_conflicts_path())?
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(PathBuf::from)
+            .collect();
+
+        Ok(Some(MergeState {
+            operation,
+            orig_head: fs::read_to_string(self.orig_head_path())?.trim().to_owned(),
+            merge_head: fs::read_to_string(self.operation_head_path(operation))?
+                .trim()
+                .to_owned(),
+            message: fs::read_to_string(self.merge_msg_path())?,
+            conflicts,
+        }))
+    }
+
+    pub(crate) fn clear_merge_state(&self) -> Result<(), Error> {
+        for path in [
+            self.merge_head_path(),
+            self.cherry_pick_head_path(),
+            self.revert_head_path(),
+            self.merge_msg_path(),
+            self.merge_conflicts_path(),
+            self.orig_head_path(),
+        ] {
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Author and the time of authoring for a commit concluding an operation. Cherry-picked
+    // commit keeps the author of the picked one, merges and reverts are authored by whoever makes
+    // them.
+    pub(crate) fn operation_author(
+        &self,
+        operation: Operation,
+        digest: &str,
+    ) -> Result<Option<(String, SystemTime)>, Error> {
+        match operation {
+            Operation::CherryPick => Ok(Some(self.authorship(digest)?)),
+            Operation::Merge | Operation::Revert => Ok(None),
+        }
+    }
+
+    // Fails if a merge, cherry-pick, revert or rebase is stopped on conflicts.
+    pub(crate) fn check_no_merge_state(&self) -> Result<(), Error> {
+        if self.rebase_dir_path().is_dir() {
+            return Err(Error::MergeInProgress("rebase"));
+        }
+
+        match self.read_merge_state()? {
+            Some(state) => Err(Error::MergeInProgress(state.operation.name())),
+            None => Ok(()),
+        }
+    }
+
+    // Conflicted paths which still have conflict markers in them. Removed ones are resolved.
+    pub(crate) fn unresolved_conflicts(&self, paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+        let mut unresolved = Vec::new();
+
+        for path in paths {
+            let full_path = self.work_dir.join(path);
+            if !full_path.is_file() {
+                continue;
+            }
+
+            let has_markers = fs::read_to_string(full_path)?.lines().any(|l| {
+                l.starts_with(OURS_MARKER) || l == BASE_SEPARATOR || l.starts_with(THEIRS_MARKER)
+            });
+            if has_markers {
+                unresolved.push(path.clone());
+            }
+        }
+
+        Ok(unresolved)
+    }
+
+    pub(crate) fn commit_parents(&self, digest: &str) -> Result<Vec<String>, Error> {
+        let commit = self.read_commit_object(digest.to_owned())?;
+
+        Ok(commit.parents().iter().map(|p| p.to_string()).collect())
+    }
+
+    // All commits reachable from the given one including itself.
+    pub(crate) fn ancestors(&self, digest: &str) -> Result<HashSet<String>, Error> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![digest.to_owned()];
+
+        while let Some(digest) = stack.pop() {
+            if seen.insert(digest.clone()) {
+                stack.extend(self.commit_parents(digest.as_str())?);
+            }
+        }
+
+        Ok(seen)
+    }
+
+    fn operation_head_path(&self, operation: Operation) -> PathBuf {
+        match operation {
+            Operation::Merge => self.merge_head_path(),
+            Operation::CherryPick => self.cherry_pick_head_path(),
+            Operation::Revert => self.revert_head_path(),
+        }
+    }
+
+    fn remove_empty_dirs(&self, file: &Path) -> Result<(), Error> {
+        for dir in file.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+
+            let full_path = self.work_dir.join(dir);
+            if fs::read_dir(&full_path)?.next().is_some() {
+                break;
+            }
+            fs::remove_dir(full_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Operation {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Three-way merge of file contents (diff3). Lines changed on one side only are taken from that
+// side, overlapping changes are put between conflict markers. Returns merged content and whether
+// there were conflicts.
+pub(crate) fn merge_lines(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    labels: (&str, &str),
+) -> (String, bool) {
+    let o: Vec<&str> = base.lines().collect();
+    let a: Vec<&str> = ours.lines().collect();
+    let b: Vec<&str> = theirs.lines().collect();
+
+    let match_a = matches(&o, &a);
+    let match_b = matches(&o, &b);
+
+    let mut out: Vec<String> = Vec::new();
+    let mut conflict = false;
+    // Whether the last lines written were put between conflict markers.
+    let mut ends_in_conflict = false;
+
+    // Number of lines already consumed from every version.
+    let (mut line_o, mut line_a, mut line_b) = (0, 0, 0);
+
+    let mut write_chunk = |o: &[&str], a: &[&str], b: &[&str]| {
+        if a == o || a == b {
+            out.extend(b.iter().map(|l| l.to_string()));
+            ends_in_conflict &= b.is_empty();
+        } else if b == o {
+            out.extend(a.iter().map(|l| l.to_string()));
+            ends_in_conflict &= a.is_empty();
+        } else {
+            conflict = true;
+            ends_in_conflict = true;
+            out.push(format!("{OURS_MARKER} {}", labels.0));
+            out.extend(a.iter().map(|l| l.to_string()));
+            out.push(BASE_SEPARATOR.to_string());
+            out.extend(b.iter().map(|l| l.to_string()));
+            out.push(format!("{THEIRS_MARKER} {}", labels.1));
+        }
+    };
+
+    loop {
+        // Length of the stable run where all three versions agree, plus one.
+        let in_bounds =
+            |i: usize| line_o + i <= o.len() || line_a + i <= a.len() || line_b + i <= b.len();
+        let mut i = 1;
+        while in_bounds(i)
+            && match_a.get(&(line_o + i)) == Some(&(line_a + i))
+            && match_b.get(&(line_o + i)) == Some(&(line_b + i))
+        {
+            i += 1;
+        }
+
+        if !in_bounds(i) {
+            write_chunk(&o[line_o..], &a[line_a..], &b[line_b..]);
+            break;
+        }
+
+        let (next_o, next_a, next_b) = if i == 1 {
+            // Versions diverge right away, skip to the next base line present in all of them.
+            let mut next = line_o + 1;
+            while next <= o.len() && !(match_a.contains_key(&next) && match_b.contains_key(&next)) {
+                next += 1;
+            }
+
+            match (match_a.get(&next), match_b.get(&next)) {
+                (Some(na), Some(nb)) => (next, *na, *nb),
+                _ => {
+                    write_chunk(&o[line_o..], &a[line_a..], &b[line_b..]);
+                    break;
+                }
+            }
+        } else {
+            (line_o + i, line_a + i, line_b + i)
+        };
+
+        write_chunk(
+            &o[line_o..next_o - 1],
+            &a[line_a..next_a - 1],
+            &b[line_b..next_b - 1],
+        );
+        line_o = next_o - 1;
+        line_a = next_a - 1;
+        line_b = next_b - 1;
+    }
+
+    // Newline at the end of a file is merged like a line of its own, the side which changed it
+    // wins. Conflict markers always end with a newline.
+    let newline = |content: &str| content.ends_with('\n');
+    let ends_with_newline = if newline(ours) == newline(base) {
+        newline(theirs)
+    } else {
+        newline(ours)
+    };
+
+    let mut merged = out.join("\n");
+    if !merged.is_empty() && (ends_with_newline || ends_in_conflict) {
+        merged.push('\n');
+    }
+
+    (merged, conflict)
+}
+
+// Both versions as a whole between conflict markers.
+fn conflict_block(ours: &str, theirs: &str, labels: (&str, &str)) -> String {
+    let mut block = vec![format!("{OURS_MARKER} {}", labels.0)];
+    block.extend(ours.lines().map(|l| l.to_string()));
+    block.push(BASE_SEPARATOR.to_string());
+    block.extend(theirs.lines().map(|l| l.to_string()));
+    block.push(format!("{THEIRS_MARKER} {}", labels.1));
+
+    block.join("\n") + "\n"
+}
+
+// Maps base lines to equal lines of another version, both numbered from one.
+fn matches(base: &[&str], other: &[&str]) -> BTreeMap<usize, usize> {
+    diff_lines(base, other)
+        .into_iter()
+        .filter_map(|e| match e {
+            Edit::Equal(o, n) => Some((o + 1, n + 1)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_lines;
+
+    const LABELS: (&str, &str) = ("HEAD", "theirs");
+
+    #[test]
+    fn merge_lines_clean() {
+        let base = "celery\ngarlic\nonions\nsalmon\ntomatoes\nwine\n";
+        let ours = "celery\nsalmon\ntomatoes\ngarlic\nonions\nwine\n";
+        let theirs = "celery\ngarlic\nonions\nsalmon\ntomatoes\nwine\nbeer\n";
+
+        let (merged, conflict) = merge_lines(base, ours, theirs, LABELS);
+        assert!(!conflict);
+        assert!(merged == "celery\nsalmon\ntomatoes\ngarlic\nonions\nwine\nbeer\n");
+
+        // Same change on both sides is not a conflict.
+        let (merged, conflict) = merge_lines("a\nb\n", "a\nc\n", "a\nc\n", LABELS);
+        assert!(!conflict);
+        assert!(merged == "a\nc\n");
+    }
+
+    #[test]
+    fn merge_lines_conflict() {
+        let base = "1\n2\n3\n";
+        let ours = "1\ntwo\n3\n";
+        let theirs = "1\nzwei\n3\n";
+
+        let (merged, conflict) = merge_lines(base, ours, theirs, LABELS);
+        assert!(conflict);
+        assert!(merged == "1\n<<<<<<< HEAD\ntwo\n=======\nzwei\n>>>>>>> theirs\n3\n");
+    }
+
+    #[test]
+    fn merge_lines_trailing_newline() {
+        // Missing newline is kept when no side adds it.
+        let (merged, conflict) = merge_lines("a\nb", "x\nb", "a\nb", LABELS);
+        assert!(!conflict);
+        assert!(merged == "x\nb");
+
+        // Adding or removing it is a change of its own.
+        let (merged, _) = merge_lines("a\nb", "x\nb", "a\nb\n", LABELS);
+        assert!(merged == "x\nb\n");
+        let (merged, _) = merge_lines("a\nb\n", "x\nb\n", "a\nb", LABELS);
+        assert!(merged == "x\nb");
+
+        // Conflict markers are never left without one.
+        let (merged, conflict) = merge_lines("a", "b", "c", LABELS);
+        assert!(conflict);
+        assert!(merged == "<<<<<<< HEAD\nb\n=======\nc\n>>>>>>> theirs\n");
+    }
+
+    #[test]
+    fn merge_lines_no_base() {
+        let (merged, conflict) = merge_lines("", "ours\n", "theirs\n", LABELS);
+        assert!(conflict);
+        assert!(merged == "<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> theirs\n");
+    }
+}
@@ -1,6 +1,6 @@
 use crate::error::Error;
 use crate::paths;
-use crate::Repo;
+use crate::{Repo, EMPTY_REF};
 
 use std::fs;
 use std::fs::File;
@@ -10,6 +10,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use flate2::{read::GzDecoder, Compression, GzBuilder};
+use itertools::Itertools;
 use sha1_smol::Sha1;
 
 #[derive(Debug, Clone)]
@@ -37,16 +38,27 @@ pub(crate) enum Object {
         content: String,
         digest: String,
     },
+    Tag {
+        // Tagged commit, tag name and tagger followed by a timestamp and a message to save and
+        // calculate digest.
+        properties: Vec<String>,
+        message: String,
+        timestamp: Duration,
+        digest: String,
+    },
 }
 
 pub(crate) type ObjectString = (String, String, String); // Object type, digest and filename.
 
+const COMMITTER_PREFIX: &str = "committer ";
+
 impl Object {
     pub(crate) fn path(&self) -> &Path {
         match self {
             Self::Commit { path, .. } => path.as_ref(),
             Self::Tree { path, .. } => path.as_ref(),
             Self::Blob { path, .. } => path.as_ref(),
+            Self::Tag { .. } => Path::new(""), // Tag doesn't belong to working directory.
         }
     }
 
@@ -55,9 +67,66 @@ impl Object {
             Self::Commit { digest, .. } => digest.as_str(),
             Self::Tree { digest, .. } => digest.as_str(),
             Self::Blob { digest, .. } => digest.as_str(),
+            Self::Tag { digest, .. } => digest.as_str(),
         }
     }
 
+    // Parent property of a commit as it is stored. It is an empty ref for the first commit in the
+    // repo and space separated digests for merge commits.
+    pub(crate) fn parent(&self) -> Result<&str, Error> {
+        self.commit_property(0)
+    }
+
+    // All the parent commit digests, empty for the first commit.
+    pub(crate) fn parents(&self) -> Vec<&str> {
+        self.parent()
+            .map(|p| p.split_whitespace().filter(|p| *p != EMPTY_REF).collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn author(&self) -> Result<&str, Error> {
+        self.commit_property(1)
+    }
+
+    // Identity and timestamp of whoever made the commit, which are the author ones unless the
+    // commit was amended, picked or rebased.
+    pub(crate) fn committer(&self) -> Result<(&str, u64), Error> {
+        let Self::Commit {
+            properties,
+            timestamp,
+            digest,
+            ..
+        } = self
+        else {
+            return Err(not_a(paths::COMMITS_DIR, self.digest()));
+        };
+
+        match properties.get(4) {
+            Some(line) => parse_committer(line).ok_or_else(|| {
+                corrupt(
+                    paths::COMMITS_DIR,
+                    digest,
+                    format!("invalid committer {line:?}"),
+                )
+            }),
+            None => Ok((self.author()?, timestamp.as_secs())),
+        }
+    }
+
+    fn commit_property(&self, i: usize) -> Result<&str, Error> {
+        let Self::Commit {
+            properties, digest, ..
+        } = self
+        else {
+            return Err(not_a(paths::COMMITS_DIR, self.digest()));
+        };
+
+        properties
+            .get(i)
+            .map(|s| s.as_str())
+            .ok_or_else(|| corrupt(paths::COMMITS_DIR, digest, "missing commit properties"))
+    }
+
     // Calculates digest string for a content of a given object type. Which is a file content for
     // blob object, and formatted list of children objects for commit and tree node. It also sorts
     // objects content. Once digest is calculated content should'nt be altered.
@@ -102,6 +171,15 @@ impl Object {
                 *content = file_content;
                 *digest = hasher.digest().to_string();
             }
+            Self::Tag {
+                properties, digest, ..
+            } => {
+                let mut hasher = Sha1::new();
+                for line in properties {
+                    hasher.update(line.as_bytes());
+                }
+                *digest = hasher.digest().to_string();
+            }
         }
 
         Ok(())
@@ -120,6 +198,7 @@ impl Object {
                 ref mut content, ..
             } => content.push(obj_str),
             Self::Blob { .. } => (), // For blob a content is what file contains.
+            Self::Tag { .. } => (),  // Tag has no children.
         }
     }
 
@@ -128,12 +207,9 @@ impl Object {
     pub(crate) fn obj_content_line(&self) -> Result<String, Error> {
         match self {
             Self::Commit { .. } => Ok(String::default()), // Commit can't be representet as an obj string.
+            Self::Tag { .. } => Ok(String::default()),    // Neither can tag.
             Self::Tree { path, digest, .. } => {
-                let file_name = path
-                    .file_name()
-                    .ok_or(Error::Unexpected)?
-                    .to_str()
-                    .ok_or(Error::Unexpected)?;
+                let file_name = file_name(path)?;
                 Ok(format!(
                     "{}\t{}\t{}",
                     paths::TREE_DIR,
@@ -142,11 +218,7 @@ impl Object {
                 ))
             }
             Self::Blob { path, digest, .. } => {
-                let file_name = path
-                    .file_name()
-                    .ok_or(Error::Unexpected)?
-                    .to_str()
-                    .ok_or(Error::Unexpected)?;
+                let file_name = file_name(path)?;
                 Ok(format!(
                     "{}\t{}\t{}",
                     paths::BLOB_DIR,
@@ -190,7 +262,7 @@ impl Repo {
                 let f = File::create(self.tree_path().join(digest))?;
 
                 let mut zipper = GzBuilder::new()
-                    .filename(path.file_name().unwrap().to_str().unwrap())
+                    .filename(file_name(path)?)
                     // TODO save dir timestamp to restore it as well
                     // .extra(timestamp.as_secs().into())
                     .write(f, Compression::default());
@@ -207,7 +279,7 @@ impl Repo {
                 let f = File::create(self.blob_path().join(digest))?;
 
                 let mut zipper = GzBuilder::new()
-                    .filename(path.file_name().unwrap().to_str().unwrap())
+                    .filename(file_name(path)?)
                     // TODO save file timestamp to restore it as well
                     // .extra(timestamp.as_secs().into())
                     .write(f, Compression::default());
@@ -215,43 +287,96 @@ impl Repo {
                 zipper.write_all(content.as_bytes())?;
                 zipper.finish()?;
             }
+            Object::Tag {
+                properties,
+                message,
+                timestamp,
+                digest,
+            } => {
+                fs::create_dir_all(self.tags_objects_path())?;
+                let f = File::create(self.tags_objects_path().join(digest))?;
+
+                let mut zipper = GzBuilder::new()
+                    .filename(digest.as_bytes())
+                    .comment(message.as_bytes())
+                    .extra(timestamp.as_secs().to_string().as_bytes())
+                    .write(f, Compression::default());
+
+                zipper.write_all(properties.join("\n").as_bytes())?;
+                zipper.finish()?;
+            }
         }
 
         Ok(())
     }
 
     pub(crate) fn read_commit_object(&self, digest: String) -> Result<Object, Error> {
-        let contents = decode_archive(self.commits_path().join(digest.clone()).as_path())?;
+        let commit_path = self.commits_path().join(digest.as_str());
+        if !commit_path.is_file() {
+            return Err(Error::CommitNotFound);
+        }
+
+        let contents = decode_archive(commit_path.as_path())?;
 
         let lines: Vec<String> = contents.split("\n").map(|s| s.to_owned()).collect();
 
         // Verify a commit has at least it's basic properties.
         if lines.len() < 4 {
-            return Err(Error::Unexpected);
+            return Err(corrupt(
+                paths::COMMITS_DIR,
+                &digest,
+                "missing commit properties",
+            ));
         }
 
+        // Committer line is there only if it differs from the author.
+        let properties_len = match lines.get(4) {
+            Some(l) if l.starts_with(COMMITTER_PREFIX) => 5,
+            _ => 4,
+        };
+
+        let timestamp = parse_timestamp(paths::COMMITS_DIR, &digest, &lines[2])?;
         let commit = Object::Commit {
             path: self.work_dir.to_owned(),
-            properties: lines[0..=3].to_vec(),
-            content: lines[4..].to_vec(),
+            properties: lines[..properties_len].to_vec(),
+            content: lines[properties_len..].to_vec(),
             message: lines[3].clone(),
-            timestamp: Duration::new(lines[2].parse::<u64>().unwrap(), 0),
+            timestamp,
             digest,
         };
 
         Ok(commit)
     }
 
-    pub(crate) fn from_tree(&self, digest: String, path: PathBuf) -> Result<Object, Error> {
-        let contents = decode_archive(self.tree_path().join(digest.clone()).as_path())?;
+    pub(crate) fn read_tag_object(&self, digest: String) -> Result<Object, Error> {
+        let contents = decode_archive(self.tags_objects_path().join(digest.as_str()).as_path())?;
 
-        let lines: Vec<String> = contents.split("\n").map(|s| s.to_owned()).collect();
+        let lines: Vec<String> = contents.split('\n').map(|s| s.to_owned()).collect();
 
-        if lines.len() < 1 {
-            return Err(Error::Unexpected);
+        // Tagged commit, name, tagger, timestamp and message.
+        if lines.len() < 5 {
+            return Err(corrupt(paths::TAG_DIR, &digest, "missing tag properties"));
         }
 
-        let children: Vec<String> = contents.split("\n").map(|s| s.to_owned()).collect();
+        let tag = Object::Tag {
+            timestamp: parse_timestamp(paths::TAG_DIR, &digest, &lines[3])?,
+            message: lines[4..].join("\n"),
+            properties: lines,
+            digest,
+        };
+
+        Ok(tag)
+    }
+
+    pub(crate) fn read_tree_object(&self, digest: String, path: PathBuf) -> Result<Object, Error> {
+        let contents = decode_archive(self.tree_path().join(digest.clone()).as_path())?;
+
+        let children: Vec<String> = contents.split('\n').map(|s| s.to_owned()).collect();
+
+        // Entries are checked up front, so a broken tree is reported before it's walked.
+        for child in children.iter().filter(|c| !c.is_empty()) {
+            parse_content_line(child)?;
+        }
 
         let tree = Object::Tree {
             path,
@@ -262,7 +387,53 @@ impl Repo {
         Ok(tree)
     }
 
-    pub(crate) fn from_blob(&self, digest: String, path: PathBuf) -> Result<Object, Error> {
+    // Object type and digest at the given path inside a commit, None if there is nothing. Only
+    // the trees along the path are read.
+    pub(crate) fn path_entry(
+        &self,
+        commit: &Object,
+        path: &Path,
+    ) -> Result<Option<(String, String)>, Error> {
+        let Object::Commit { content, .. } = commit else {
+            return Err(not_a(paths::COMMITS_DIR, commit.digest()));
+        };
+
+        let mut lines = content.clone();
+        let mut components = path.iter().peekable();
+        while let Some(name) = components.next() {
+            let name = name.to_str().ok_or(Error::UnsupportedEncoding)?;
+
+            let mut entry = None;
+            for l in lines.iter().filter(|l| !l.is_empty()) {
+                let parts = parse_content_line(l)?;
+                if parts.2 == name {
+                    entry = Some(parts);
+                    break;
+                }
+            }
+
+            let Some((kind, digest, _)) = entry else {
+                return Ok(None);
+            };
+
+            if components.peek().is_none() {
+                return Ok(Some((kind, digest)));
+            }
+
+            if kind != paths::TREE_DIR {
+                return Ok(None);
+            }
+
+            lines = match self.read_tree_object(digest.clone(), PathBuf::new())? {
+                Object::Tree { content, .. } => content,
+                _ => return Err(not_a(paths::TREE_DIR, &digest)),
+            };
+        }
+
+        Ok(None)
+    }
+
+    pub(crate) fn read_blob_object(&self, digest: String, path: PathBuf) -> Result<Object, Error> {
         let content = decode_archive(self.blob_path().join(digest.clone()).as_path())?;
 
         let blob = Object::Blob {
@@ -274,6 +445,66 @@ impl Repo {
 
         Ok(blob)
     }
+
+    pub(crate) fn read_blob_content(&self, digest: &str) -> Result<String, Error> {
+        decode_archive(self.blob_path().join(digest).as_path())
+    }
+}
+
+// Commit property line with a committer different from the author, going after the message.
+pub(crate) fn format_committer(identity: &str, timestamp: u64) -> String {
+    format!("{COMMITTER_PREFIX}{timestamp} {identity}")
+}
+
+fn parse_committer(line: &str) -> Option<(&str, u64)> {
+    let (timestamp, identity) = line.strip_prefix(COMMITTER_PREFIX)?.split_once(' ')?;
+
+    Some((identity, timestamp.parse().ok()?))
+}
+
+// Splits a line of commit or tree content into an object type, its digest and a filename. Only
+// trees and blobs can be listed.
+pub(crate) fn parse_content_line(line: &str) -> Result<ObjectString, Error> {
+    let invalid = || Error::InvalidTreeEntry(line.to_owned());
+
+    let entry: ObjectString = line
+        .split('\t')
+        .map(|s| s.to_string())
+        .collect_tuple()
+        .ok_or_else(invalid)?;
+    if entry.0 != paths::TREE_DIR && entry.0 != paths::BLOB_DIR {
+        return Err(invalid());
+    }
+
+    Ok(entry)
+}
+
+// Name a tree or a blob is saved with and listed by in its parent.
+fn file_name(path: &Path) -> Result<&str, Error> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::PathNotUtf8(path.to_owned()))
+}
+
+fn parse_timestamp(kind: &'static str, digest: &str, line: &str) -> Result<Duration, Error> {
+    let secs = line
+        .parse::<u64>()
+        .map_err(|_| corrupt(kind, digest, format!("invalid timestamp {line:?}")))?;
+
+    Ok(Duration::new(secs, 0))
+}
+
+// Error for an object which is read as one type, but turns out to be another.
+pub(crate) fn not_a(kind: &'static str, digest: &str) -> Error {
+    corrupt(kind, digest, format!("not a {kind}"))
+}
+
+pub(crate) fn corrupt(kind: &'static str, digest: &str, reason: impl Into<String>) -> Error {
+    Error::CorruptObject {
+        digest: digest.to_owned(),
+        kind,
+        reason: reason.into(),
+    }
 }
 
 fn format_commit_properties(props: Vec<String>) -> String {
@@ -282,21 +513,24 @@ fn format_commit_properties(props: Vec<String>) -> String {
     joined
 }
 
-fn decode_archive(path: &Path) -> Result<String, Error> {
+pub(crate) fn decode_archive(path: &Path) -> Result<String, Error> {
     let f = File::open(path)?;
     let mut decoder = GzDecoder::new(f);
     let mut contents = String::new();
     decoder.read_to_string(&mut contents)?;
     // Headers are not in use for now.
-    // let header = decoder.header().ok_or(Error::Unexpected)?;
+    // let header = decoder.header();
 
     Ok(contents)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::format_commit_properties;
     use super::Object;
+    use super::{format_commit_properties, format_committer, parse_content_line};
+    use crate::error::Error;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
     use std::path::PathBuf;
     use std::time::Duration;
 
@@ -392,7 +626,7 @@ mod tests {
 
         let content_line = tree.obj_content_line();
         assert!(content_line.is_ok());
-        assert!(content_line.unwrap() == String::from("tree\tdigest\ttmp"));
+        assert!(content_line.unwrap() == "tree\tdigest\ttmp");
     }
 
     #[test]
@@ -406,13 +640,80 @@ mod tests {
 
         let content_line = blob.obj_content_line();
         assert!(content_line.is_ok());
-        assert!(content_line.unwrap() == String::from("blob\tdigest\todyssey.txt"));
+        assert!(content_line.unwrap() == "blob\tdigest\todyssey.txt");
+    }
+
+    #[test]
+    fn obj_content_line_not_utf8() {
+        let path = PathBuf::from(OsStr::from_bytes(b"/tmp/\xff.txt"));
+        let blob = Object::Blob {
+            path: path.clone(),
+            full_path: PathBuf::default(),
+            content: String::default(),
+            digest: String::from("digest"),
+        };
+
+        assert!(matches!(blob.obj_content_line(), Err(Error::PathNotUtf8(p)) if p == path));
+    }
+
+    #[test]
+    fn test_parse_content_line() {
+        let parts = parse_content_line("blob\tdigest\todyssey.txt").unwrap();
+        assert!(parts == ("blob".into(), "digest".into(), "odyssey.txt".into()));
+
+        assert!(matches!(
+            parse_content_line("blob\tdigest"),
+            Err(Error::InvalidTreeEntry(_))
+        ));
+        assert!(matches!(
+            parse_content_line("a\tb\tc\td"),
+            Err(Error::InvalidTreeEntry(_))
+        ));
+        assert!(matches!(
+            parse_content_line("link\tdigest\tname"),
+            Err(Error::InvalidTreeEntry(_))
+        ));
     }
 
     #[test]
     fn test_format_commit_properties() {
         let props: Vec<String> = vec!["hello".to_string(), "my".to_string(), "dear".to_string()];
         let result = format_commit_properties(props);
-        assert!(result == "hello\nmy\ndear\n".to_string());
+        assert!(result == "hello\nmy\ndear\n");
+    }
+
+    #[test]
+    fn commit_committer() {
+        let mut properties = vec![
+            String::from("1234567890abcdefghij"),
+            String::from("Jane <jane@example.com>"),
+            String::from("1680961369"),
+            String::from("message"),
+        ];
+        let commit = |properties: &[String]| Object::Commit {
+            path: PathBuf::from("/tmp"),
+            content: Vec::new(),
+            properties: properties.to_vec(),
+            message: String::from("message"),
+            timestamp: Duration::new(1680961369, 0),
+            digest: String::default(),
+        };
+
+        assert!(
+            commit(&properties).committer().ok() == Some(("Jane <jane@example.com>", 1680961369))
+        );
+
+        properties.push(format_committer("John Doe <john@example.com>", 1680962000));
+        assert!(properties[4] == "committer 1680962000 John Doe <john@example.com>");
+        assert!(
+            commit(&properties).committer().ok()
+                == Some(("John Doe <john@example.com>", 1680962000))
+        );
+
+        properties[4] = String::from("committer yesterday John Doe");
+        assert!(matches!(
+            commit(&properties).committer(),
+            Err(Error::CorruptObject { reason, .. }) if reason.starts_with("invalid committer")
+        ));
     }
 }
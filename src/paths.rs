@@ -4,14 +4,35 @@ use crate::Repo;
 use std::path::{Path, PathBuf};
 
 pub(crate) const REPO_DIR: &str = ".get";
+pub(crate) const CONFIG_FILE: &str = ".get.toml";
 pub(crate) const HEAD_FILE: &str = "HEAD";
 pub(crate) const LOG_FILE: &str = "LOG";
+pub(crate) const MERGE_HEAD_FILE: &str = "MERGE_HEAD";
+pub(crate) const CHERRY_PICK_HEAD_FILE: &str = "CHERRY_PICK_HEAD";
+pub(crate) const REVERT_HEAD_FILE: &str = "REVERT_HEAD";
+pub(crate) const ORIG_HEAD_FILE: &str = "ORIG_HEAD";
+pub(crate) const MERGE_MSG_FILE: &str = "MERGE_MSG";
+pub(crate) const MERGE_CONFLICTS_FILE: &str = "MERGE_CONFLICTS";
+pub(crate) const REBASE_DIR: &str = "rebase";
+pub(crate) const BISECT_DIR: &str = "bisect";
 pub(crate) const OBJECTS_DIR: &str = "objects";
 pub(crate) const COMMITS_DIR: &str = "commit";
 pub(crate) const TREE_DIR: &str = "tree";
 pub(crate) const BLOB_DIR: &str = "blob";
+pub(crate) const TAG_DIR: &str = "tag";
+pub(crate) const REFS_DIR: &str = "refs";
+pub(crate) const HEADS_DIR: &str = "heads";
+pub(crate) const TAGS_DIR: &str = "tags";
+pub(crate) const STASH_FILE: &str = "stash";
 
 impl Repo {
+    // Path relative to the working directory root. Relative paths are taken as they are.
+    pub(crate) fn relative_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(self.work_dir.as_path())
+            .unwrap_or(path)
+            .to_owned()
+    }
+
     pub(crate) fn commits_path(&self) -> PathBuf {
         self.work_dir
             .join(REPO_DIR)
@@ -33,9 +54,57 @@ impl Repo {
             .join(BLOB_DIR)
     }
 
+    pub(crate) fn tags_objects_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(OBJECTS_DIR).join(TAG_DIR)
+    }
+
+    pub(crate) fn refs_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(REFS_DIR)
+    }
+
+    pub(crate) fn tags_path(&self) -> PathBuf {
+        self.refs_path().join(TAGS_DIR)
+    }
+
+    pub(crate) fn stash_path(&self) -> PathBuf {
+        self.refs_path().join(STASH_FILE)
+    }
+
     pub(crate) fn log_path(&self) -> PathBuf {
         self.work_dir.join(REPO_DIR).join(LOG_FILE)
     }
+
+    pub(crate) fn merge_head_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(MERGE_HEAD_FILE)
+    }
+
+    pub(crate) fn cherry_pick_head_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(CHERRY_PICK_HEAD_FILE)
+    }
+
+    pub(crate) fn revert_head_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(REVERT_HEAD_FILE)
+    }
+
+    pub(crate) fn rebase_dir_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(REBASE_DIR)
+    }
+
+    pub(crate) fn bisect_dir_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(BISECT_DIR)
+    }
+
+    pub(crate) fn orig_head_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(ORIG_HEAD_FILE)
+    }
+
+    pub(crate) fn merge_msg_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(MERGE_MSG_FILE)
+    }
+
+    pub(crate) fn merge_conflicts_path(&self) -> PathBuf {
+        self.work_dir.join(REPO_DIR).join(MERGE_CONFLICTS_FILE)
+    }
 }
 
 pub(crate) fn head_path(work_dir: &Path) -> PathBuf {
@@ -52,7 +121,7 @@ pub(crate) fn repo_dir(cur_dir: &Path) -> Result<PathBuf, Error> {
     Err(Error::NotAGetRepo)
 }
 
-pub(crate) fn check_no_repo_dir(cur_dir: &PathBuf) -> Result<(), Error> {
+pub(crate) fn check_no_repo_dir(cur_dir: &Path) -> Result<(), Error> {
     if cur_dir.join(REPO_DIR).is_dir() {
         return Err(Error::RepoAlreadyExist);
     }
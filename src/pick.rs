@@ -0,0 +1,89 @@
+use crate::error::Error;
+use crate::merge::{MergeOutcome, MergeState, Operation};
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::{read_head, Repo, EMPTY_REF};
+
+use std::time::SystemTime;
+
+impl Repo {
+    /// Applies the changes a commit made relative to its first parent on top of HEAD as a new
+    /// commit with the same message. Changes are merged line by line, overlapping ones stop the
+    /// cherry-pick with conflict markers until it is continued or aborted.
+    pub fn cherry_pick(&self, digest: &str, now: SystemTime) -> Result<MergeOutcome, Error> {
+        let message = match self.read_commit_object(digest.to_owned())? {
+            Object::Commit { message, .. } => message,
+            _ => return Err(not_a(paths::COMMITS_DIR, digest)),
+        };
+
+        self.apply_commit(digest, Operation::CherryPick, message, now)
+    }
+
+    /// Undoes the changes a commit made relative to its first parent with a new commit on top of
+    /// HEAD. Works the same way as cherry-pick with the changes inverted.
+    pub fn revert(&self, digest: &str, now: SystemTime) -> Result<MergeOutcome, Error> {
+        let message = format!("Revert commit {digest}");
+
+        self.apply_commit(digest, Operation::Revert, message, now)
+    }
+
+    fn apply_commit(
+        &self,
+        digest: &str,
+        operation: Operation,
+        message: String,
+        now: SystemTime,
+    ) -> Result<MergeOutcome, Error> {
+        self.check_no_merge_state()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        let commit = self.read_commit_object(digest.to_owned())?;
+        let parent = commit
+            .parents()
+            .first()
+            .map_or(EMPTY_REF, |p| *p)
+            .to_owned();
+
+        self.check_clean()?;
+
+        // Revert is a cherry-pick of the changes from the commit back to its parent.
+        let (base, theirs) = match operation {
+            Operation::Revert => (digest.to_owned(), parent),
+            _ => (parent, digest.to_owned()),
+        };
+
+        let merged = self.merge_commits(
+            base.as_str(),
+            head.as_str(),
+            theirs.as_str(),
+            ("HEAD", digest),
+        )?;
+        self.write_merged(head.as_str(), &merged.files)?;
+
+        let base = Some(base).filter(|b| b != EMPTY_REF);
+
+        if !merged.conflicts.is_empty() {
+            self.write_merge_state(&MergeState {
+                operation,
+                orig_head: head,
+                merge_head: digest.to_owned(),
+                message,
+                conflicts: merged.conflicts.clone(),
+            })?;
+
+            return Ok(MergeOutcome::Conflicts {
+                base,
+                conflicts: merged.conflicts,
+            });
+        }
+
+        let author = self.operation_author(operation, digest)?;
+        let commit = self.commit_on(head, message.as_str(), operation.name(), author, now)?;
+
+        Ok(MergeOutcome::Merged { base, commit })
+    }
+}
@@ -0,0 +1,203 @@
+use crate::error::Error;
+use crate::object::{decode_archive, not_a, parse_content_line, Object};
+use crate::paths;
+use crate::{Repo, DEFAULT_FILE_PERMISSIONS};
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const TREE_MODE: u32 = 0o040000;
+const BLOB_MODE: u32 = 0o100000 | DEFAULT_FILE_PERMISSIONS;
+
+/// Type of an object in the object storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+/// A single line of a tree listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub mode: u32,
+    pub kind: ObjectKind,
+    pub digest: String,
+    pub path: PathBuf,
+}
+
+impl ObjectKind {
+    fn dir(&self) -> &'static str {
+        match self {
+            Self::Commit => paths::COMMITS_DIR,
+            Self::Tree => paths::TREE_DIR,
+            Self::Blob => paths::BLOB_DIR,
+            Self::Tag => paths::TAG_DIR,
+        }
+    }
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.dir())
+    }
+}
+
+impl fmt::Display for TreeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:06o} {} {}\t{}",
+            self.mode,
+            self.kind,
+            self.digest,
+            self.path.display()
+        )
+    }
+}
+
+impl Repo {
+    /// Finds out an object type by looking it up in all the object directories.
+    pub fn object_kind(&self, digest: &str) -> Result<ObjectKind, Error> {
+        for kind in [
+            ObjectKind::Commit,
+            ObjectKind::Tree,
+            ObjectKind::Blob,
+            ObjectKind::Tag,
+        ] {
+            if self.object_path(kind, digest).is_file() {
+                return Ok(kind);
+            }
+        }
+
+        Err(Error::ObjectNotFound(digest.to_owned()))
+    }
+
+    /// Returns an object content exactly as it is stored, just unzipped.
+    pub fn cat_object(&self, digest: &str) -> Result<String, Error> {
+        let kind = self.object_kind(digest)?;
+
+        decode_archive(self.object_path(kind, digest).as_path())
+    }
+
+    /// Lists a tree or a root tree of a commit or a tagged commit. Recursive listing descends
+    /// into subtrees and shows only blobs with paths relative to the listed tree.
+    pub fn ls_tree(&self, digest: &str, recursive: bool) -> Result<Vec<TreeEntry>, Error> {
+        let digest = self.peel_tag(digest)?;
+        let digest = digest.as_str();
+
+        let content = match self.object_kind(digest)? {
+            ObjectKind::Commit => match self.read_commit_object(digest.to_owned())? {
+                Object::Commit { content, .. } => content,
+                _ => return Err(not_a(paths::COMMITS_DIR, digest)),
+            },
+            ObjectKind::Tree => match self.read_tree_object(digest.to_owned(), PathBuf::new())? {
+                Object::Tree { content, .. } => content,
+                _ => return Err(not_a(paths::TREE_DIR, digest)),
+            },
+            ObjectKind::Blob | ObjectKind::Tag => return Err(Error::NotATree(digest.to_owned())),
+        };
+
+        let mut entries = Vec::new();
+        self.list_entries(&content, Path::new(""), recursive, &mut entries)?;
+
+        Ok(entries)
+    }
+
+    /// Calculates a digest of a file the same way it is done for blobs on commit. Optionally
+    /// saves it to the object storage.
+    pub fn hash_object(&self, file: &Path, write: bool) -> Result<String, Error> {
+        let mut blob = Object::Blob {
+            path: PathBuf::from(
+                file.file_name()
+                    .ok_or_else(|| Error::NotAFile(file.to_owned()))?,
+            ),
+            full_path: file.to_owned(),
+            content: String::default(),
+            digest: String::default(),
+        };
+
+        blob.update_digest()?;
+
+        if write {
+            self.save_object(&blob)?;
+        }
+
+        Ok(blob.digest().to_owned())
+    }
+
+    fn object_path(&self, kind: ObjectKind, digest: &str) -> PathBuf {
+        match kind {
+            ObjectKind::Commit => self.commits_path().join(digest),
+            ObjectKind::Tree => self.tree_path().join(digest),
+            ObjectKind::Blob => self.blob_path().join(digest),
+            ObjectKind::Tag => self.tags_objects_path().join(digest),
+        }
+    }
+
+    fn list_entries(
+        &self,
+        lines: &[String],
+        parent_path: &Path,
+        recursive: bool,
+        entries: &mut Vec<TreeEntry>,
+    ) -> Result<(), Error> {
+        let mut lines: Vec<&String> = lines.iter().filter(|l| !l.is_empty()).collect();
+        lines.sort_by_key(|l| l.split('\t').nth(2).map(|s| s.to_owned()));
+
+        for l in lines {
+            let parts = parse_content_line(l)?;
+            let path = parent_path.join(parts.2);
+
+            match parts.0.as_ref() {
+                paths::TREE_DIR if recursive => {
+                    if let Object::Tree { content, .. } =
+                        self.read_tree_object(parts.1, path.clone())?
+                    {
+                        self.list_entries(&content, &path, recursive, entries)?;
+                    }
+                }
+                paths::TREE_DIR => entries.push(TreeEntry {
+                    mode: TREE_MODE,
+                    kind: ObjectKind::Tree,
+                    digest: parts.1,
+                    path,
+                }),
+                paths::BLOB_DIR => entries.push(TreeEntry {
+                    mode: BLOB_MODE,
+                    kind: ObjectKind::Blob,
+                    digest: parts.1,
+                    path,
+                }),
+                _ => return Err(Error::InvalidTreeEntry(l.to_string())),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_entry_display() {
+        let entry = TreeEntry {
+            mode: TREE_MODE,
+            kind: ObjectKind::Tree,
+            digest: String::from("digest"),
+            path: PathBuf::from("src/bin"),
+        };
+        assert!(entry.to_string() == "040000 tree digest\tsrc/bin");
+
+        let entry = TreeEntry {
+            mode: BLOB_MODE,
+            kind: ObjectKind::Blob,
+            digest: String::from("digest"),
+            path: PathBuf::from("main.rs"),
+        };
+        assert!(entry.to_string() == "100644 blob digest\tmain.rs");
+    }
+}
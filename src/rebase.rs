@@ -0,0 +1,308 @@
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::{read_head, Repo, EMPTY_REF};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ONTO_FILE: &str = "onto";
+const ORIG_HEAD_FILE: &str = "orig_head";
+const TODO_FILE: &str = "todo";
+const CURRENT_FILE: &str = "current";
+const CONFLICTS_FILE: &str = "conflicts";
+
+/// Result of replaying HEAD commits on top of another commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebaseOutcome {
+    /// HEAD already contains the upstream commit, nothing to replay.
+    UpToDate,
+    /// HEAD had no commits of its own, so it was simply moved to upstream.
+    FastForward(String),
+    /// All the commits were replayed, holds the new HEAD.
+    Rebased(String),
+    /// Rebase stopped on a commit whose changes conflict, then it can be continued, the commit
+    /// skipped or the whole rebase aborted.
+    Conflicts {
+        commit: String,
+        conflicts: Vec<PathBuf>,
+    },
+}
+
+// Rebase stopped on conflicts. Stored as separate files in `.get/rebase` until it is finished or
+// aborted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RebaseState {
+    onto: String,
+    // HEAD before the rebase started.
+    orig_head: String,
+    // Commits left to replay, oldest first.
+    todo: Vec<String>,
+    // Commit being replayed when rebase stopped.
+    current: String,
+    conflicts: Vec<PathBuf>,
+}
+
+impl Repo {
+    /// Replays commits made since the common ancestor with upstream on top of it one by one,
+    /// keeping their authors, timestamps and messages. Merge commits are dropped and so are the
+    /// commits whose changes are already in upstream. Rebase stops on conflicts until it is
+    /// continued, the commit is skipped or the rebase is aborted. Old HEAD is recorded in the
+    /// reflog, so the rebase can be undone by restoring it.
+    pub fn rebase(&self, upstream: &str, now: SystemTime) -> Result<RebaseOutcome, Error> {
+        self.check_no_merge_state()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        // Make sure it is a commit before doing anything.
+        let _ = self.read_commit_object(upstream.to_owned())?;
+
+        let base = self.merge_base(head.as_str(), upstream)?;
+        if base.as_deref() == Some(upstream) {
+            return Ok(RebaseOutcome::UpToDate);
+        }
+
+        self.check_clean()?;
+
+        self.checkout_files(upstream)?;
+        let message = format!("checkout {upstream}");
+        self.move_head(upstream, "rebase (start)", message.as_str(), now)?;
+
+        if base.as_deref() == Some(head.as_str()) {
+            self.finish_rebase(head.as_str(), upstream, now)?;
+            return Ok(RebaseOutcome::FastForward(upstream.to_owned()));
+        }
+
+        let todo = self.commits_to_replay(head.as_str(), upstream)?;
+
+        self.replay(upstream, head.as_str(), todo, now)
+    }
+
+    /// Commits the resolved changes of the commit rebase stopped on and goes on with the rest.
+    pub fn rebase_continue(&self, now: SystemTime) -> Result<RebaseOutcome, Error> {
+        let state = self.read_rebase_state()?.ok_or(Error::NoRebaseInProgress)?;
+
+        let unresolved = self.unresolved_conflicts(&state.conflicts)?;
+        if !unresolved.is_empty() {
+            return Err(Error::UnresolvedConflicts(unresolved));
+        }
+
+        self.pick_resolved(state.current.as_str(), now)?;
+        self.clear_rebase_state()?;
+
+        self.replay(
+            state.onto.as_str(),
+            state.orig_head.as_str(),
+            state.todo,
+            now,
+        )
+    }
+
+    /// Drops the commit rebase stopped on and goes on with the rest.
+    pub fn rebase_skip(&self, now: SystemTime) -> Result<RebaseOutcome, Error> {
+        let state = self.read_rebase_state()?.ok_or(Error::NoRebaseInProgress)?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        self.checkout_files(head.as_str())?;
+        self.clear_rebase_state()?;
+
+        self.replay(
+            state.onto.as_str(),
+            state.orig_head.as_str(),
+            state.todo,
+            now,
+        )
+    }
+
+    /// Drops a stopped rebase, moving HEAD back to where it was before it started.
+    pub fn rebase_abort(&self, now: SystemTime) -> Result<String, Error> {
+        let state = self.read_rebase_state()?.ok_or(Error::NoRebaseInProgress)?;
+
+        self.checkout_files(state.orig_head.as_str())?;
+        let message = format!("returning to {}", state.orig_head);
+        self.move_head(
+            state.orig_head.as_str(),
+            "rebase (abort)",
+            message.as_str(),
+            now,
+        )?;
+        self.clear_rebase_state()?;
+
+        Ok(state.orig_head)
+    }
+
+    // Picks commits one by one on top of HEAD, stopping on the first one with conflicts.
+    fn replay(
+        &self,
+        onto: &str,
+        orig_head: &str,
+        todo: Vec<String>,
+        now: SystemTime,
+    ) -> Result<RebaseOutcome, Error> {
+        let mut todo = todo.into_iter();
+
+        while let Some(commit) = todo.next() {
+            let head = read_head(self.work_dir.as_path())?;
+            let parent = self
+                .commit_parents(commit.as_str())?
+                .first()
+                .map_or(EMPTY_REF.to_owned(), |p| p.to_owned());
+
+            let merged = self.merge_commits(
+                parent.as_str(),
+                head.as_str(),
+                commit.as_str(),
+                ("HEAD", commit.as_str()),
+            )?;
+            self.write_merged(head.as_str(), &merged.files)?;
+
+            if !merged.conflicts.is_empty() {
+                self.write_rebase_state(&RebaseState {
+                    onto: onto.to_owned(),
+                    orig_head: orig_head.to_owned(),
+                    todo: todo.collect(),
+                    current: commit.clone(),
+                    conflicts: merged.conflicts.clone(),
+                })?;
+
+                return Ok(RebaseOutcome::Conflicts {
+                    commit,
+                    conflicts: merged.conflicts,
+                });
+            }
+
+            self.pick_resolved(commit.as_str(), now)?;
+        }
+
+        let head = read_head(self.work_dir.as_path())?;
+        self.finish_rebase(orig_head, onto, now)?;
+
+        Ok(RebaseOutcome::Rebased(head))
+    }
+
+    // Commits working directory files on top of HEAD with the metadata of the replayed commit.
+    // Nothing is committed if there are no changes left.
+    fn pick_resolved(&self, commit: &str, now: SystemTime) -> Result<(), Error> {
+        let head = read_head(self.work_dir.as_path())?;
+        if self.working_files()? == self.commit_files(head.as_str())? {
+            return Ok(());
+        }
+
+        let object = self.read_commit_object(commit.to_owned())?;
+        let author = object.author()?;
+        let Object::Commit {
+            ref message,
+            ref timestamp,
+            ..
+        } = object
+        else {
+            return Err(not_a(paths::COMMITS_DIR, commit));
+        };
+
+        let authored = UNIX_EPOCH + *timestamp;
+        let digest = self.save_commit_as(head, author, authored, message.as_str(), now)?;
+        self.move_head(digest.as_str(), "rebase (pick)", message.as_str(), now)?;
+
+        Ok(())
+    }
+
+    // Reflog entry going from the old HEAD straight to the rebased one, so the whole rebase can
+    // be undone at once.
+    fn finish_rebase(&self, orig_head: &str, onto: &str, now: SystemTime) -> Result<(), Error> {
+        let head = read_head(self.work_dir.as_path())?;
+        let message = format!("rebased {orig_head} onto {onto}");
+
+        self.append_log(orig_head, head.as_str(), "rebase (finish)", &message, now)
+    }
+
+    // Commits reachable from HEAD but not from upstream, parents before children. Merge commits
+    // are left out since their changes come with the other commits.
+    fn commits_to_replay(&self, head: &str, upstream: &str) -> Result<Vec<String>, Error> {
+        let upstream_commits = self.ancestors(upstream)?;
+
+        let mut commits = Vec::new();
+        let mut seen = HashSet::new();
+        // Depth-first walk emitting a commit after all its parents.
+        let mut stack = vec![(head.to_owned(), false)];
+        while let Some((digest, parents_done)) = stack.pop() {
+            let parents = self.commit_parents(digest.as_str())?;
+
+            if parents_done {
+                if parents.len() < 2 {
+                    commits.push(digest);
+                }
+                continue;
+            }
+
+            if upstream_commits.contains(&digest) || !seen.insert(digest.clone()) {
+                continue;
+            }
+
+            stack.push((digest, true));
+            stack.extend(parents.into_iter().rev().map(|p| (p, false)));
+        }
+
+        Ok(commits)
+    }
+
+    fn write_rebase_state(&self, state: &RebaseState) -> Result<(), Error> {
+        let dir = self.rebase_dir_path();
+        let conflicts: Vec<&str> = state
+            .conflicts
+            .iter()
+            .map(|p| p.to_str().ok_or(Error::UnsupportedEncoding))
+            .collect::<Result<_, _>>()?;
+
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(ONTO_FILE), state.onto.as_str())?;
+        fs::write(dir.join(ORIG_HEAD_FILE), state.orig_head.as_str())?;
+        fs::write(dir.join(TODO_FILE), state.todo.join("\n"))?;
+        fs::write(dir.join(CURRENT_FILE), state.current.as_str())?;
+        fs::write(dir.join(CONFLICTS_FILE), conflicts.join("\n"))?;
+
+        Ok(())
+    }
+
+    fn read_rebase_state(&self) -> Result<Option<RebaseState>, Error> {
+        let dir = self.rebase_dir_path();
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+
+        let read_line = |name: &str| -> Result<String, Error> {
+            Ok(fs::read_to_string(dir.join(name))?.trim().to_owned())
+        };
+        let read_lines = |name: &str| -> Result<Vec<String>, Error> {
+            Ok(fs::read_to_string(dir.join(name))?
+                .lines()
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_owned())
+                .collect())
+        };
+
+        Ok(Some(RebaseState {
+            onto: read_line(ONTO_FILE)?,
+            orig_head: read_line(ORIG_HEAD_FILE)?,
+            todo: read_lines(TODO_FILE)?,
+            current: read_line(CURRENT_FILE)?,
+            conflicts: read_lines(CONFLICTS_FILE)?
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+        }))
+    }
+
+    fn clear_rebase_state(&self) -> Result<(), Error> {
+        let dir = self.rebase_dir_path();
+        if dir.is_dir() {
+            fs::remove_dir_all(dir)?;
+        }
+
+        Ok(())
+    }
+}
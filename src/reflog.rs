@@ -0,0 +1,132 @@
+use crate::date::since_epoch;
+use crate::error::Error;
+use crate::Repo;
+
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::SystemTime;
+
+/// A single HEAD movement recorded in `.get/LOG`. Every entry is stored as one tab separated line:
+/// old digest, new digest, author, timestamp, operation and message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub old: String,
+    pub new: String,
+    pub author: String,
+    pub timestamp: u64,
+    pub operation: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            self.old,
+            self.new,
+            sanitize(&self.author),
+            self.timestamp,
+            self.operation,
+            sanitize(&self.message),
+        )
+    }
+
+    fn from_line(line: &str) -> Result<LogEntry, Error> {
+        let parts: Vec<&str> = line.splitn(6, '\t').collect();
+        if parts.len() != 6 {
+            return Err(Error::InvalidReflogEntry(line.to_owned()));
+        }
+
+        Ok(LogEntry {
+            old: parts[0].to_owned(),
+            new: parts[1].to_owned(),
+            author: parts[2].to_owned(),
+            timestamp: parts[3]
+                .parse::<u64>()
+                .map_err(|_| Error::InvalidReflogEntry(line.to_owned()))?,
+            operation: parts[4].to_owned(),
+            message: parts[5].to_owned(),
+        })
+    }
+}
+
+impl Repo {
+    /// Returns all the recorded HEAD movements, the most recent one first.
+    pub fn reflog(&self) -> Result<Vec<LogEntry>, Error> {
+        let contents = fs::read_to_string(self.log_path())?;
+
+        let mut entries = contents
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(LogEntry::from_line)
+            .collect::<Result<Vec<LogEntry>, Error>>()?;
+        entries.reverse();
+
+        Ok(entries)
+    }
+
+    pub(crate) fn append_log(
+        &self,
+        old: &str,
+        new: &str,
+        operation: &str,
+        message: &str,
+        now: SystemTime,
+    ) -> Result<(), Error> {
+        let timestamp = since_epoch(now)?;
+
+        let entry = LogEntry {
+            old: old.to_owned(),
+            new: new.to_owned(),
+            author: self.config.author.to_string(),
+            timestamp: timestamp.as_secs(),
+            operation: operation.to_owned(),
+            message: message.to_owned(),
+        };
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path())?;
+        f.write_all(entry.to_line().as_bytes())?;
+
+        Ok(())
+    }
+}
+
+// Log is line and tab separated, so neither can appear inside a field.
+fn sanitize(s: &str) -> String {
+    s.replace(['\n', '\t'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogEntry;
+
+    #[test]
+    fn log_entry_roundtrip() {
+        let entry = LogEntry {
+            old: String::from("0000000000000000000000000000000000000000"),
+            new: String::from("410f802802a2135fb469b540deb03d9b22156cc4"),
+            author: String::from("rakhmaninov"),
+            timestamp: 1680961369,
+            operation: String::from("commit"),
+            message: String::from("prelude in\tc sharp minor"),
+        };
+
+        let line = entry.to_line();
+        assert!(line.ends_with('\n'));
+
+        let parsed = LogEntry::from_line(line.trim_end()).unwrap();
+        assert!(parsed.message == "prelude in c sharp minor");
+        assert!(parsed.new == entry.new);
+        assert!(parsed.timestamp == entry.timestamp);
+    }
+
+    #[test]
+    fn log_entry_malformed() {
+        assert!(LogEntry::from_line("garbage").is_err());
+        assert!(LogEntry::from_line("a\tb\tc\tnot a number\te\tf").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::Error;
+use crate::paths;
+use crate::tag::check_ref_name;
+use crate::{read_head, Repo, EMPTY_REF};
+
+use std::fs;
+
+const HEAD: &str = "HEAD";
+// Shorter prefixes match too many objects to be useful.
+const MIN_PREFIX_LEN: usize = 4;
+
+impl Repo {
+    /// Resolves a revision expression to a commit digest. Supported expressions are `HEAD` (or
+    /// `@`), reflog entries `@{n}` and `HEAD@{n}`, branch and tag names, full digests and their
+    /// unique prefixes, each optionally followed by any combination of `~n` (n-th first parent
+    /// ancestor) and `^n` (n-th parent) suffixes.
+    pub fn resolve_rev(&self, rev: &str) -> Result<String, Error> {
+        let not_found = || Error::RevisionNotFound(rev.to_owned());
+
+        let split = rev.find(['~', '^']).unwrap_or(rev.len());
+        let (base, mut suffix) = rev.split_at(split);
+
+        let mut digest = self.resolve_base(base, rev)?;
+
+        while !suffix.is_empty() {
+            let op = suffix.as_bytes()[0];
+            let digits_len = suffix[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(suffix.len() - 1);
+            let digits = &suffix[1..1 + digits_len];
+            suffix = &suffix[1 + digits_len..];
+
+            let n = match digits {
+                "" => 1,
+                d => d.parse::<usize>().map_err(|_| not_found())?,
+            };
+
+            match op {
+                b'~' => {
+                    for _ in 0..n {
+                        digest = self.nth_parent(&digest, 1)?.ok_or_else(not_found)?;
+                    }
+                }
+                b'^' if n == 0 => (),
+                b'^' => digest = self.nth_parent(&digest, n)?.ok_or_else(not_found)?,
+                _ => return Err(not_found()),
+            }
+        }
+
+        Ok(digest)
+    }
+
+    fn resolve_base(&self, base: &str, rev: &str) -> Result<String, Error> {
+        let not_found = || Error::RevisionNotFound(rev.to_owned());
+
+        if base == HEAD || base == "@" {
+            let head = read_head(self.work_dir.as_path())?;
+            if head == EMPTY_REF {
+                return Err(not_found());
+            }
+
+            return Ok(head);
+        }
+
+        if let Some(n) = reflog_index(base) {
+            let entries = self.reflog()?;
+            return entries.get(n).map(|e| e.new.clone()).ok_or_else(not_found);
+        }
+
+        // Names which can't be refs are not looked up, so nothing outside refs is read.
+        if check_ref_name(base).is_ok() {
+            let heads_path = self.refs_path().join(paths::HEADS_DIR);
+            for ref_path in [heads_path.join(base), self.tags_path().join(base)] {
+                if ref_path.is_file() {
+                    let digest = fs::read_to_string(ref_path)?.trim().to_owned();
+                    return self.peel_tag(digest.as_str());
+                }
+            }
+        }
+
+        self.resolve_prefix(base)
+            .and_then(|d| d.ok_or_else(not_found))
+    }
+
+    // Looks for a commit with a digest starting with the given prefix.
+    fn resolve_prefix(&self, prefix: &str) -> Result<Option<String>, Error> {
+        if prefix.len() < MIN_PREFIX_LEN || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+
+        let mut candidates = Vec::new();
+        for entry in fs::read_dir(self.commits_path())? {
+            let name = entry?.file_name();
+            if let Some(name) = name.to_str().filter(|n| n.starts_with(prefix)) {
+                candidates.push(name.to_owned());
+            }
+        }
+
+        match candidates.len() {
+            0 => Ok(None),
+            1 => Ok(candidates.pop()),
+            _ => {
+                candidates.sort();
+                Err(Error::AmbiguousRevision(prefix.to_owned(), candidates))
+            }
+        }
+    }
+
+    // None if there is no such commit or it has fewer parents.
+    fn nth_parent(&self, digest: &str, n: usize) -> Result<Option<String>, Error> {
+        let commit = match self.read_commit_object(digest.to_owned()) {
+            Ok(commit) => commit,
+            Err(Error::CommitNotFound) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        Ok(commit.parents().get(n - 1).map(|p| p.to_string()))
+    }
+}
+
+// Parses `@{n}` and `HEAD@{n}` forms.
+fn reflog_index(base: &str) -> Option<usize> {
+    base.strip_prefix(HEAD)
+        .unwrap_or(base)
+        .strip_prefix("@{")?
+        .strip_suffix('}')?
+        .parse::<usize>()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reflog_index;
+
+    #[test]
+    fn test_reflog_index() {
+        assert!(reflog_index("@{0}") == Some(0));
+        assert!(reflog_index("HEAD@{12}") == Some(12));
+        assert!(reflog_index("HEAD").is_none());
+        assert!(reflog_index("@{x}").is_none());
+        assert!(reflog_index("master@{1}").is_none());
+    }
+}
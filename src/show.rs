@@ -0,0 +1,130 @@
+use crate::date::format_timestamp;
+use crate::diff::{self, Change};
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::Repo;
+use crate::EMPTY_REF;
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// Commit metadata with the list of paths changed relative to its first parent and an optional
+/// patch.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitDetails {
+    pub digest: String,
+    /// Empty for the first commit in a repo, two for a merge commit.
+    pub parents: Vec<String>,
+    pub author: String,
+    /// When the commit was authored.
+    pub timestamp: u64,
+    /// The same as the author unless the commit was amended, picked or rebased.
+    pub committer: String,
+    pub commit_timestamp: u64,
+    pub message: String,
+    pub changes: Vec<Change>,
+    pub patch: Option<String>,
+}
+
+impl Repo {
+    pub fn show(&self, digest: &str, with_patch: bool) -> Result<CommitDetails, Error> {
+        let commit = self.read_commit_object(digest.to_owned())?;
+
+        let Object::Commit {
+            ref message,
+            ref timestamp,
+            ..
+        } = commit
+        else {
+            return Err(not_a(paths::COMMITS_DIR, digest));
+        };
+
+        let parents: Vec<String> = commit.parents().iter().map(|p| p.to_string()).collect();
+        let first_parent = parents.first().map_or(EMPTY_REF, |p| p.as_str());
+        let old_files = self.commit_files(first_parent)?;
+        let new_files = self.commit_files(digest)?;
+        let changes = diff::diff_files(&old_files, &new_files);
+
+        let patch = if with_patch {
+            Some(self.format_patch(&changes)?)
+        } else {
+            None
+        };
+
+        let (committer, commit_timestamp) = commit.committer()?;
+
+        Ok(CommitDetails {
+            digest: digest.to_owned(),
+            parents,
+            author: commit.author()?.to_owned(),
+            timestamp: timestamp.as_secs(),
+            committer: committer.to_owned(),
+            commit_timestamp,
+            message: message.clone(),
+            changes,
+            patch,
+        })
+    }
+
+    // Unified patch for all the given changes, reading blob contents from the object storage.
+    pub(crate) fn format_patch(&self, changes: &[Change]) -> Result<String, Error> {
+        let mut patch = String::new();
+
+        for c in changes {
+            let path = c.path.to_str().ok_or(Error::UnsupportedEncoding)?;
+
+            let (old_name, old_content) = match &c.old {
+                Some(d) => (format!("a/{path}"), self.read_blob_content(d)?),
+                None => (String::from("/dev/null"), String::new()),
+            };
+            let (new_name, new_content) = match &c.new {
+                Some(d) => (format!("b/{path}"), self.read_blob_content(d)?),
+                None => (String::from("/dev/null"), String::new()),
+            };
+
+            patch.push_str(&diff::unified_diff(
+                &old_name,
+                &new_name,
+                &old_content,
+                &new_content,
+            ));
+        }
+
+        Ok(patch)
+    }
+}
+
+impl fmt::Display for CommitDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "commit {}", self.digest)?;
+        for parent in self.parents.iter() {
+            writeln!(f, "parent {parent}")?;
+        }
+        writeln!(f, "author {}", self.author)?;
+        writeln!(f, "date   {}", format_timestamp(self.timestamp))?;
+        if (self.committer.as_str(), self.commit_timestamp)
+            != (self.author.as_str(), self.timestamp)
+        {
+            writeln!(f, "committer {}", self.committer)?;
+            writeln!(f, "committed {}", format_timestamp(self.commit_timestamp))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "    {}", self.message)?;
+
+        if !self.changes.is_empty() {
+            writeln!(f)?;
+            for c in self.changes.iter() {
+                writeln!(f, "{}\t{}", c.kind, c.path.display())?;
+            }
+        }
+
+        if let Some(patch) = self.patch.as_ref().filter(|p| !p.is_empty()) {
+            writeln!(f)?;
+            write!(f, "{patch}")?;
+        }
+
+        Ok(())
+    }
+}
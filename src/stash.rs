@@ -0,0 +1,157 @@
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::{read_head, Repo, EMPTY_REF};
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Uncommitted changes saved aside. Stored as a commit with the working directory files whose
+/// parent is HEAD at the moment of saving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    pub digest: String,
+    /// Commit the changes were made on top of.
+    pub head: String,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+impl Repo {
+    /// Saves working directory changes as a stash entry and brings the files back to HEAD.
+    pub fn stash_push(&self, msg: Option<&str>, now: SystemTime) -> Result<StashEntry, Error> {
+        self.check_no_merge_state()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        if head == EMPTY_REF {
+            return Err(Error::NoCommitsYet);
+        }
+
+        if self.working_files()? == self.commit_files(head.as_str())? {
+            return Err(Error::NoLocalChanges);
+        }
+
+        let message = match msg {
+            Some(m) => format!("On {head}: {m}"),
+            None => format!("WIP on {head}"),
+        };
+        let author = self.config.author.to_string();
+        let digest = self.save_commit_as(head.clone(), &author, now, message.as_str(), now)?;
+
+        let mut digests = self.stash_digests()?;
+        digests.insert(0, digest.clone());
+        self.write_stash_digests(&digests)?;
+
+        self.checkout_files(head.as_str())?;
+
+        self.stash_entry(digest.as_str())
+    }
+
+    /// Lists stash entries, the most recent first.
+    pub fn stash_list(&self) -> Result<Vec<StashEntry>, Error> {
+        self.stash_digests()?
+            .iter()
+            .map(|d| self.stash_entry(d.as_str()))
+            .collect()
+    }
+
+    /// Applies the changes of a stash entry on top of HEAD with a three-way merge against the
+    /// commit they were made on. Returns paths with conflicts written into them, the entry is
+    /// kept in any case.
+    pub fn stash_apply(&self, index: usize) -> Result<Vec<PathBuf>, Error> {
+        self.check_no_merge_state()?;
+
+        let digest = self
+            .stash_digests()?
+            .get(index)
+            .cloned()
+            .ok_or(Error::StashNotFound(index))?;
+        let entry = self.stash_entry(digest.as_str())?;
+
+        self.check_clean()?;
+
+        let head = read_head(self.work_dir.as_path())?;
+        let label = format!("stash@{{{index}}}");
+        let merged = self.merge_commits(
+            entry.head.as_str(),
+            head.as_str(),
+            digest.as_str(),
+            ("HEAD", label.as_str()),
+        )?;
+        self.write_merged(head.as_str(), &merged.files)?;
+
+        Ok(merged.conflicts)
+    }
+
+    /// Applies a stash entry and drops it unless there were conflicts.
+    pub fn stash_pop(&self, index: usize) -> Result<Vec<PathBuf>, Error> {
+        let conflicts = self.stash_apply(index)?;
+
+        if conflicts.is_empty() {
+            self.stash_drop(index)?;
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Removes a stash entry from the list. Its commit stays in the storage.
+    pub fn stash_drop(&self, index: usize) -> Result<StashEntry, Error> {
+        let mut digests = self.stash_digests()?;
+        if index >= digests.len() {
+            return Err(Error::StashNotFound(index));
+        }
+
+        let digest = digests.remove(index);
+        self.write_stash_digests(&digests)?;
+
+        self.stash_entry(digest.as_str())
+    }
+
+    fn stash_entry(&self, digest: &str) -> Result<StashEntry, Error> {
+        let commit = self.read_commit_object(digest.to_owned())?;
+
+        let Object::Commit {
+            ref message,
+            ref timestamp,
+            ..
+        } = commit
+        else {
+            return Err(not_a(paths::COMMITS_DIR, digest));
+        };
+
+        Ok(StashEntry {
+            digest: digest.to_owned(),
+            head: commit.parent()?.to_owned(),
+            message: message.clone(),
+            timestamp: timestamp.as_secs(),
+        })
+    }
+
+    // Stash commits, one per line with the most recent first.
+    fn stash_digests(&self) -> Result<Vec<String>, Error> {
+        if !self.stash_path().is_file() {
+            return Ok(Vec::new());
+        }
+
+        Ok(fs::read_to_string(self.stash_path())?
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_owned())
+            .collect())
+    }
+
+    fn write_stash_digests(&self, digests: &[String]) -> Result<(), Error> {
+        if digests.is_empty() {
+            if self.stash_path().is_file() {
+                fs::remove_file(self.stash_path())?;
+            }
+            return Ok(());
+        }
+
+        fs::create_dir_all(self.refs_path())?;
+        fs::write(self.stash_path(), digests.join("\n"))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use crate::diff::{self, Change};
+use crate::error::Error;
+use crate::{read_head, Repo};
+
+impl Repo {
+    /// Compares the working directory with HEAD. Files which are not in HEAD, and not ignored,
+    /// are added, so they are what the next commit would add.
+    pub fn status(&self) -> Result<Vec<Change>, Error> {
+        let head = read_head(self.work_dir.as_path())?;
+
+        Ok(diff::diff_files(
+            &self.commit_files(head.as_str())?,
+            &self.working_files()?,
+        ))
+    }
+}
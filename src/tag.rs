@@ -0,0 +1,206 @@
+use crate::date::since_epoch;
+use crate::error::Error;
+use crate::object::{not_a, Object};
+use crate::paths;
+use crate::Repo;
+
+use std::fs;
+use std::time::SystemTime;
+
+/// A named reference to a commit. Annotated tags are separate objects pointing to a commit and
+/// carrying a tagger, a timestamp and a message, lightweight ones are just names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    /// Digest of the tagged commit.
+    pub target: String,
+    pub annotation: Option<TagAnnotation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagAnnotation {
+    /// Digest of the tag object itself.
+    pub digest: String,
+    pub tagger: String,
+    pub timestamp: u64,
+    pub message: String,
+}
+
+impl Repo {
+    /// Creates a tag for a commit. Tag is annotated if a message is given.
+    pub fn create_tag(
+        &self,
+        name: &str,
+        target: &str,
+        message: Option<&str>,
+        now: SystemTime,
+    ) -> Result<Tag, Error> {
+        check_ref_name(name)?;
+
+        let ref_path = self.tags_path().join(name);
+        if ref_path.exists() {
+            return Err(Error::TagAlreadyExists(name.to_owned()));
+        }
+
+        // Make sure we are tagging an existing commit.
+        let _ = self.read_commit_object(target.to_owned())?;
+
+        let annotation = match message {
+            Some(message) => {
+                let timestamp = since_epoch(now)?;
+
+                let mut tag = Object::Tag {
+                    properties: vec![
+                        target.to_owned(),
+                        name.to_owned(),
+                        self.config.author.to_string(),
+                        timestamp.as_secs().to_string(),
+                        message.to_owned(),
+                    ],
+                    message: message.to_owned(),
+                    timestamp,
+                    digest: String::default(),
+                };
+                tag.update_digest()?;
+                self.save_object(&tag)?;
+
+                Some(TagAnnotation {
+                    digest: tag.digest().to_owned(),
+                    tagger: self.config.author.to_string(),
+                    timestamp: timestamp.as_secs(),
+                    message: message.to_owned(),
+                })
+            }
+            None => None,
+        };
+
+        let ref_value = annotation.as_ref().map_or(target, |a| a.digest.as_str());
+        fs::create_dir_all(self.tags_path())?;
+        fs::write(ref_path, ref_value)?;
+
+        Ok(Tag {
+            name: name.to_owned(),
+            target: target.to_owned(),
+            annotation,
+        })
+    }
+
+    /// Lists all the tags ordered by name.
+    pub fn tags(&self) -> Result<Vec<Tag>, Error> {
+        let mut tags = Vec::new();
+
+        if !self.tags_path().is_dir() {
+            return Ok(tags);
+        }
+
+        for entry in fs::read_dir(self.tags_path())? {
+            let name = entry?
+                .file_name()
+                .into_string()
+                .map_err(|_| Error::UnsupportedEncoding)?;
+            tags.push(self.read_tag(name.as_str())?);
+        }
+
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(tags)
+    }
+
+    /// Deletes a tag reference. Annotated tag object stays in the storage.
+    pub fn delete_tag(&self, name: &str) -> Result<Tag, Error> {
+        let tag = self.read_tag(name)?;
+        fs::remove_file(self.tags_path().join(name))?;
+
+        Ok(tag)
+    }
+
+    fn read_tag(&self, name: &str) -> Result<Tag, Error> {
+        let ref_path = self.tags_path().join(name);
+        if !ref_path.is_file() {
+            return Err(Error::TagNotFound(name.to_owned()));
+        }
+
+        let digest = fs::read_to_string(ref_path)?.trim().to_owned();
+
+        let (target, annotation) = match self.tag_annotation(digest.as_str())? {
+            Some((target, annotation)) => (target, Some(annotation)),
+            None => (digest, None),
+        };
+
+        Ok(Tag {
+            name: name.to_owned(),
+            target,
+            annotation,
+        })
+    }
+
+    // Follows a reference value to a commit, reading annotated tag object if that's what it
+    // points to.
+    pub(crate) fn peel_tag(&self, digest: &str) -> Result<String, Error> {
+        match self.tag_annotation(digest)? {
+            Some((target, _)) => Ok(target),
+            None => Ok(digest.to_owned()),
+        }
+    }
+
+    fn tag_annotation(&self, digest: &str) -> Result<Option<(String, TagAnnotation)>, Error> {
+        if !self.tags_objects_path().join(digest).is_file() {
+            return Ok(None);
+        }
+
+        match self.read_tag_object(digest.to_owned())? {
+            Object::Tag {
+                properties,
+                message,
+                timestamp,
+                digest,
+            } => Ok(Some((
+                properties[0].clone(),
+                TagAnnotation {
+                    digest,
+                    tagger: properties[2].clone(),
+                    timestamp: timestamp.as_secs(),
+                    message,
+                },
+            ))),
+            _ => Err(not_a(paths::TAG_DIR, digest)),
+        }
+    }
+}
+
+// Names are used as file names and in revision expressions, so they can't contain path
+// separators or revision syntax.
+pub(crate) fn check_ref_name(name: &str) -> Result<(), Error> {
+    let valid = !name.is_empty()
+        && !name.starts_with(['.', '-'])
+        && name != "HEAD"
+        && !name.contains("@{")
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "/\\~^:?*[".contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidRefName(name.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_ref_name;
+
+    #[test]
+    fn test_check_ref_name() {
+        assert!(check_ref_name("v1.2.0").is_ok());
+        assert!(check_ref_name("release-candidate_2").is_ok());
+
+        assert!(check_ref_name("").is_err());
+        assert!(check_ref_name("HEAD").is_err());
+        assert!(check_ref_name(".hidden").is_err());
+        assert!(check_ref_name("../escape").is_err());
+        assert!(check_ref_name("v1~2").is_err());
+        assert!(check_ref_name("with space").is_err());
+        assert!(check_ref_name("v1@{0}").is_err());
+    }
+}
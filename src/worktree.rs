@@ -1,16 +1,17 @@
+use crate::date::since_epoch;
 use crate::error::Error;
-use crate::object::{Object, ObjectString};
+use crate::ignore::IgnoreRules;
+use crate::object::{format_committer, not_a, parse_content_line, Object};
 use crate::paths;
 use crate::Repo;
-use crate::{DEFAULT_DIR_PERMISSIONS, DEFAULT_FILE_PERMISSIONS, DEFAULT_IGNORE};
+use crate::{DEFAULT_DIR_PERMISSIONS, DEFAULT_FILE_PERMISSIONS, EMPTY_REF};
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use itertools::Itertools;
-
 type NodeId = usize;
 
 #[derive(Debug, Clone)]
@@ -32,26 +33,46 @@ pub(crate) struct RepoWithState {
 }
 
 impl RepoWithState {
+    // Commit of the working directory files on top of the repo head. It's committed by the
+    // configured identity now, which is only saved if it differs from the author.
     pub(crate) fn from_files(
         repo: Repo,
         message: &str,
+        author: &str,
+        authored: SystemTime,
         now: SystemTime,
     ) -> Result<RepoWithState, Error> {
-        let timestamp = now
-            .duration_since(UNIX_EPOCH)
-            .map_err(|_| Error::Unexpected)?;
+        let rules = repo.ignore_rules()?;
+        RepoWithState::from_files_with(repo, message, author, authored, now, &rules)
+    }
 
-        let author: &str = repo.config.author.as_ref();
+    // Same as `from_files`, but files are ignored by the given rules instead of the repo ones.
+    fn from_files_with(
+        repo: Repo,
+        message: &str,
+        author: &str,
+        authored: SystemTime,
+        now: SystemTime,
+        rules: &IgnoreRules,
+    ) -> Result<RepoWithState, Error> {
+        let timestamp = since_epoch(authored)?;
+        let committed = since_epoch(now)?.as_secs();
+
+        let mut properties = vec![
+            repo.head.clone(),
+            author.to_string(),
+            timestamp.as_secs().to_string(),
+            message.to_string(),
+        ];
+        let committer = repo.config.author.to_string();
+        if (committer.as_str(), committed) != (author, timestamp.as_secs()) {
+            properties.push(format_committer(committer.as_str(), committed));
+        }
 
         let commit = Object::Commit {
             path: repo.work_dir.clone(),
             content: Vec::new(),
-            properties: vec![
-                repo.head.clone(),
-                author.to_string(),
-                timestamp.as_secs().to_string(),
-                message.to_string(),
-            ],
+            properties,
             message: message.to_string(),
             timestamp,
             digest: String::default(),
@@ -64,7 +85,7 @@ impl RepoWithState {
 
         let mut wt = Worktree(vec![node]);
 
-        build_tree_from_files(&mut wt, 0, &repo)?;
+        build_tree_from_files(&mut wt, 0, &repo, rules)?;
 
         wt.0[0].obj.update_digest()?;
 
@@ -72,10 +93,10 @@ impl RepoWithState {
     }
 
     pub(crate) fn from_commit(repo: Repo, digest: String) -> Result<RepoWithState, Error> {
-        let commit = repo.read_commit_object(digest)?;
+        let commit = repo.read_commit_object(digest.clone())?;
 
         if !matches!(commit, Object::Commit { .. }) {
-            return Err(Error::Unexpected);
+            return Err(not_a(paths::COMMITS_DIR, digest.as_str()));
         }
 
         let node = Node {
@@ -91,9 +112,7 @@ impl RepoWithState {
     }
 
     pub(crate) fn save_commit(&self) -> Result<&str, Error> {
-        let working_dir = self.wt.0[0].obj.path();
-
-        self.save_all_children(working_dir, 0)?;
+        self.save_all_children(0)?;
 
         Ok(self.wt.0[0].obj.digest())
     }
@@ -101,7 +120,7 @@ impl RepoWithState {
     pub(crate) fn restore_files(self) -> Result<(), Error> {
         for node in self.wt.0.into_iter() {
             match node.obj {
-                Object::Commit { .. } => (),
+                Object::Commit { .. } | Object::Tag { .. } => (),
                 Object::Tree { path, .. } => {
                     let path_to_restore = self.repo.work_dir.join(path);
                     fs::create_dir_all(&path_to_restore)?;
@@ -124,11 +143,11 @@ impl RepoWithState {
         Ok(())
     }
 
-    fn save_all_children(&self, dir: &Path, cursor: usize) -> Result<(), Error> {
+    fn save_all_children(&self, cursor: usize) -> Result<(), Error> {
         self.repo.save_object(&self.wt.0[cursor].obj)?;
 
         for i in self.wt.0[cursor].children.as_slice() {
-            self.save_all_children(dir, *i)?;
+            self.save_all_children(*i)?;
         }
 
         Ok(())
@@ -136,38 +155,108 @@ impl RepoWithState {
 }
 
 impl Repo {
+    // Flattens working directory files, except ignored ones, into a map of paths to blob digests
+    // the same way `commit_files` does for a commit.
+    pub(crate) fn working_files(&self) -> Result<BTreeMap<PathBuf, String>, Error> {
+        self.working_files_with(&self.ignore_rules()?)
+    }
+
+    // Same as `working_files` with the given ignore rules instead of the repo ones.
+    pub(crate) fn working_files_with(
+        &self,
+        rules: &IgnoreRules,
+    ) -> Result<BTreeMap<PathBuf, String>, Error> {
+        let author = self.config.author.to_string();
+        let state = RepoWithState::from_files_with(
+            self.clone(),
+            "",
+            author.as_str(),
+            UNIX_EPOCH,
+            UNIX_EPOCH,
+            rules,
+        )?;
+
+        let files = state
+            .wt
+            .0
+            .into_iter()
+            .filter_map(|node| match node.obj {
+                Object::Blob { path, digest, .. } => Some((path, digest)),
+                _ => None,
+            })
+            .collect();
+
+        Ok(files)
+    }
+
+    // Flattens a commit into a map of file paths relative to the working directory to their blob
+    // digests. Empty ref gives an empty map, so the first commit can be compared to nothing.
+    pub(crate) fn commit_files(&self, digest: &str) -> Result<BTreeMap<PathBuf, String>, Error> {
+        let mut files = BTreeMap::new();
+
+        if digest == EMPTY_REF {
+            return Ok(files);
+        }
+
+        if let Object::Commit { content, .. } = self.read_commit_object(digest.to_owned())? {
+            self.collect_files(&content, Path::new(""), &mut files)?;
+        }
+
+        Ok(files)
+    }
+
+    fn collect_files(
+        &self,
+        lines: &[String],
+        parent_path: &Path,
+        files: &mut BTreeMap<PathBuf, String>,
+    ) -> Result<(), Error> {
+        for l in lines.iter().filter(|l| !l.is_empty()) {
+            let parts = parse_content_line(l)?;
+            let path = parent_path.join(parts.2);
+
+            match parts.0.as_ref() {
+                paths::TREE_DIR => {
+                    if let Object::Tree { content, .. } =
+                        self.read_tree_object(parts.1, path.clone())?
+                    {
+                        self.collect_files(&content, &path, files)?;
+                    }
+                }
+                paths::BLOB_DIR => {
+                    files.insert(path, parts.1);
+                }
+                _ => return Err(Error::InvalidTreeEntry(l.to_owned())),
+            }
+        }
+
+        Ok(())
+    }
+
     fn build_children(&self, lines: Vec<String>, parent_path: PathBuf) -> Result<Vec<Node>, Error> {
         let mut res = Vec::<Node>::new();
 
         for l in lines {
-            let parts: ObjectString = l
-                .split("\t")
-                .map(|s| s.to_string())
-                .collect_tuple()
-                .unwrap();
+            let parts = parse_content_line(&l)?;
 
             let node = match parts.0.as_ref() {
                 paths::TREE_DIR => {
-                    let tree = self.from_tree(parts.1, parent_path.join(parts.2))?;
+                    let tree = self.read_tree_object(parts.1, parent_path.join(parts.2))?;
 
-                    let node = Node {
+                    Node {
                         children: Vec::new(),
                         obj: tree,
-                    };
-
-                    node
+                    }
                 }
                 paths::BLOB_DIR => {
-                    let blob = self.from_blob(parts.1, parent_path.join(parts.2))?;
+                    let blob = self.read_blob_object(parts.1, parent_path.join(parts.2))?;
 
-                    let node = Node {
+                    Node {
                         children: Vec::new(),
                         obj: blob,
-                    };
-
-                    node
+                    }
                 }
-                _ => unreachable!(),
+                _ => return Err(Error::InvalidTreeEntry(l)),
             };
 
             res.push(node);
@@ -181,27 +270,26 @@ impl Worktree {
     fn restore_tree_from_storage(&mut self, repo: &Repo, i: NodeId) -> Result<(), Error> {
         let mut children: Vec<Node>;
 
-        match &self.0.get(i).ok_or(Error::Unexpected)?.obj {
+        match &self.0[i].obj {
             Object::Commit { content, path, .. } => {
                 children = repo.build_children(content.clone(), path.clone())?;
             }
             Object::Tree { content, path, .. } => {
                 children = repo.build_children(content.clone(), path.clone())?;
             }
-            Object::Blob { .. } => {
+            Object::Blob { .. } | Object::Tag { .. } => {
                 children = Vec::<Node>::new();
             }
         }
 
-        if children.len() > 0 {
+        if !children.is_empty() {
+            // Only the children appended here, the arena keeps growing with their own children.
+            let first = self.0.len();
+            let count = children.len();
             self.0.append(&mut children);
 
-            for ix in (i + 1)..self.0.len() {
-                self.0
-                    .get_mut(i)
-                    .ok_or(Error::Unexpected)?
-                    .children
-                    .push(ix);
+            for ix in first..first + count {
+                self.0[i].children.push(ix);
                 self.restore_tree_from_storage(repo, ix)?;
             }
         }
@@ -210,52 +298,57 @@ impl Worktree {
     }
 }
 
-pub(crate) fn clean_before_restore(p: &Path, repo: &Repo) -> Result<(), Error> {
-    let entries = fs::read_dir(p)?.map(|e| e.unwrap());
-
-    for e in entries {
-        if is_ignored(&e.path(), &repo.config.ignore, DEFAULT_IGNORE) {
+pub(crate) fn clean_before_restore(
+    p: &Path,
+    repo: &Repo,
+    rules: &IgnoreRules,
+) -> Result<(), Error> {
+    for entry in fs::read_dir(p)? {
+        let e = entry?;
+        let ftype = e.file_type()?;
+        if is_ignored(&e.path(), ftype.is_dir(), repo, rules) {
             continue;
         }
 
-        let ftype = e.file_type()?;
         if ftype.is_dir() {
-            clean_before_restore(&e.path(), repo)?;
-            if fs::read_dir(&e.path()).into_iter().count() == 0 {
+            clean_before_restore(&e.path(), repo, rules)?;
+            if fs::read_dir(e.path()).into_iter().count() == 0 {
                 fs::remove_dir(e.path())?;
             }
         } else if ftype.is_file() {
             fs::remove_file(e.path())?;
-        } else if ftype.is_symlink() {
-            unimplemented!("get: we don't deal with symlinks here, please use real CVS like git");
+        } else {
+            return Err(Error::UnsupportedFileType(e.path()));
         }
     }
 
     Ok(())
 }
 
-fn build_tree_from_files(wt: &mut Worktree, current: NodeId, repo: &Repo) -> Result<(), Error> {
-    let mut new_cur: usize = Default::default();
-
+fn build_tree_from_files(
+    wt: &mut Worktree,
+    current: NodeId,
+    repo: &Repo,
+    rules: &IgnoreRules,
+) -> Result<(), Error> {
     let entries = fs::read_dir(repo.work_dir.join(wt.0[current].obj.path()))?;
 
     for entry in entries {
         let e = entry?;
 
-        if is_ignored(&e.path(), &repo.config.ignore, DEFAULT_IGNORE) {
+        if is_ignored(&e.path(), e.file_type()?.is_dir(), repo, rules) {
             continue;
         }
 
         let full_path = e.path();
 
-        let relative_path = full_path
-            .strip_prefix(repo.work_dir.as_path())
-            .map_err(|_| Error::Unexpected)?;
+        let relative_path = repo.relative_path(&full_path);
 
+        let new_cur;
         let ftype = e.file_type()?;
         if ftype.is_dir() {
             let tree = Object::Tree {
-                path: relative_path.to_owned(),
+                path: relative_path,
                 content: Vec::new(),
                 digest: String::default(),
             };
@@ -270,10 +363,10 @@ fn build_tree_from_files(wt: &mut Worktree, current: NodeId, repo: &Repo) -> Res
 
             wt.0[current].children.push(new_cur); // Update parent's children with new node.
 
-            build_tree_from_files(wt, new_cur, repo)?;
+            build_tree_from_files(wt, new_cur, repo, rules)?;
         } else if ftype.is_file() {
             let blob = Object::Blob {
-                path: relative_path.to_owned(),
+                path: relative_path,
                 full_path,
                 content: String::default(),
                 digest: String::default(),
@@ -290,8 +383,8 @@ fn build_tree_from_files(wt: &mut Worktree, current: NodeId, repo: &Repo) -> Res
             wt.0[current].children.push(new_cur); // Update parent's children with new node.
 
             wt.0[new_cur].obj.update_digest()?;
-        } else if ftype.is_symlink() {
-            unimplemented!("get: we don't deal with symlinks here, please use real CVS like git")
+        } else {
+            return Err(Error::UnsupportedFileType(full_path));
         }
 
         // Append a parent object content with new child.
@@ -306,24 +399,9 @@ fn build_tree_from_files(wt: &mut Worktree, current: NodeId, repo: &Repo) -> Res
     Ok(())
 }
 
-fn is_ignored(path: &PathBuf, ignored: &Vec<String>, default_ignored: &[&str]) -> bool {
-    for pattern in ignored.iter() {
-        for segment in path.components() {
-            if segment == Component::Normal(pattern.as_ref()) {
-                return true;
-            }
-        }
-    }
-
-    for pattern in default_ignored.iter() {
-        for segment in path.components() {
-            if segment == Component::Normal(pattern.as_ref()) {
-                return true;
-            }
-        }
-    }
-
-    false
+// Checks a working directory entry against the rules, which are relative to the repo root.
+fn is_ignored(path: &Path, is_dir: bool, repo: &Repo, rules: &IgnoreRules) -> bool {
+    rules.is_ignored(&repo.relative_path(path), is_dir)
 }
 
 #[cfg(test)]
@@ -332,28 +410,22 @@ mod tests {
 
     #[test]
     fn test_is_ignored() {
-        let ignore: Vec<String> = vec![
-            ".git".to_string(),
-            ".gitignore".to_string(),
-            "target".to_string(),
-            ".get".to_string(),
-        ];
-
-        const DEFAULT_IGNORE: &[&str] = &[".get", ".get.toml"]; // Default ignore patterns.
+        let mut rules = IgnoreRules::new([".git", ".gitignore", "target", ".get"]);
+        rules.extend(crate::DEFAULT_IGNORE.iter().copied(), "", None);
 
         let path = PathBuf::from("./hello/iamnot/ignore");
-        assert!(!is_ignored(&path, &ignore, DEFAULT_IGNORE));
+        assert!(!rules.is_ignored(&path, false));
 
         let path = PathBuf::from("./edgecase/hello.get");
-        assert!(!is_ignored(&path, &ignore, DEFAULT_IGNORE));
+        assert!(!rules.is_ignored(&path, false));
 
         let path = PathBuf::from("./oneanotheredgecase/mytarget/hey.rs");
-        assert!(!is_ignored(&path, &ignore, DEFAULT_IGNORE));
+        assert!(!rules.is_ignored(&path, false));
 
         let path = PathBuf::from("./dir/target/hello");
-        assert!(is_ignored(&path, &ignore, DEFAULT_IGNORE));
+        assert!(rules.is_ignored(&path, false));
 
         let path = PathBuf::from("./dir/.git/hello");
-        assert!(is_ignored(&path, &ignore, DEFAULT_IGNORE));
+        assert!(rules.is_ignored(&path, false));
     }
 }
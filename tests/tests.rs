@@ -1,13 +1,16 @@
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
+use flate2::{write::GzEncoder, Compression};
 use pretty_assertions::assert_eq;
+use serde_json::json;
 use tempdir::TempDir;
 use walkdir::WalkDir;
 
 const FIRST_COMMIT_DIGEST: &str = "410f802802a2135fb469b540deb03d9b22156cc4";
-const SECOND_COMMIT_DIGEST: &str = "b049bd6b279fd006a75fb516f7f9c957d9422e9e";
+const SECOND_COMMIT_DIGEST: &str = "f3e2e7175083265ebf0fd760931d31c2c3c1221d";
 
 #[test]
 fn repo_workflow() {
@@ -24,6 +27,8 @@ fn repo_workflow() {
     assert!(working_dir.as_path().join(".get/objects/commit").is_dir());
     assert!(working_dir.as_path().join(".get/objects/tree").is_dir());
     assert!(working_dir.as_path().join(".get/objects/blob").is_dir());
+    assert!(working_dir.as_path().join(".get/objects/tag").is_dir());
+    assert!(working_dir.as_path().join(".get/refs/tags").is_dir());
     assert!(working_dir.as_path().join(".get/HEAD").is_file());
     assert!(working_dir.as_path().join(".get/LOG").is_file());
 
@@ -42,6 +47,40 @@ fn repo_workflow() {
     assert!(cur_head.is_ok());
     assert_eq!(cur_head.unwrap(), FIRST_COMMIT_DIGEST);
 
+    // Low level access to the object storage.
+    assert_eq!(
+        repo.object_kind(FIRST_COMMIT_DIGEST).unwrap(),
+        get::ObjectKind::Commit
+    );
+    assert!(repo.object_kind("no such digest").is_err());
+    let listing: Vec<String> = repo
+        .ls_tree(FIRST_COMMIT_DIGEST, true)
+        .unwrap()
+        .iter()
+        .map(|e| format!("{} {}", e.kind, e.path.display()))
+        .collect();
+    assert_eq!(
+        listing,
+        vec![
+            "blob test_file.txt",
+            "blob testdir/nested/test_file3.txt",
+            "blob testdir/nested/test_file4.txt",
+            "blob testdir/test_file1.txt",
+            "blob testdir/test_file2.txt",
+        ]
+    );
+    let blob_digest = repo
+        .hash_object(&working_dir.join("test_file.txt"), false)
+        .unwrap();
+    assert_eq!(
+        repo.object_kind(&blob_digest).unwrap(),
+        get::ObjectKind::Blob
+    );
+    assert_eq!(
+        repo.cat_object(&blob_digest).unwrap(),
+        "thats\nall,\nfolks!"
+    );
+
     // Init again and fail since repo is alread initialized.
     assert!(get::Repo::init(&mut working_dir).is_err());
 
@@ -64,8 +103,109 @@ fn repo_workflow() {
     assert!(cur_head.is_ok());
     assert_eq!(cur_head.unwrap(), SECOND_COMMIT_DIGEST,);
 
+    // Resolve revision expressions.
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), SECOND_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("HEAD~").unwrap(), FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("HEAD^").unwrap(), FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("@{1}").unwrap(), FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("f3e2e71~1").unwrap(), FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("HEAD~1^0").unwrap(), FIRST_COMMIT_DIGEST);
+    assert!(repo.resolve_rev("HEAD~2").is_err());
+    assert!(repo.resolve_rev("f3e").is_err());
+    assert!(repo.resolve_rev("no-such-branch").is_err());
+    // Only refs are read as refs.
+    assert!(matches!(
+        repo.resolve_rev("../../HEAD"),
+        Err(get::error::Error::RevisionNotFound(_))
+    ));
+
+    // Tag both commits, the first one with an annotation.
+    let lightweight = repo
+        .create_tag("v0.2.0", SECOND_COMMIT_DIGEST, None, timestamp)
+        .unwrap();
+    assert_eq!(lightweight.annotation, None);
+    let annotated = repo
+        .create_tag(
+            "v0.1.0",
+            FIRST_COMMIT_DIGEST,
+            Some("first release"),
+            timestamp,
+        )
+        .unwrap();
+    let annotation = annotated.annotation.expect("tag should be annotated");
+    assert_eq!(annotation.tagger, "Vitalii Shvedchenko");
+    assert_eq!(annotation.message, "first release");
+    assert_eq!(
+        repo.object_kind(&annotation.digest).unwrap(),
+        get::ObjectKind::Tag
+    );
+    assert!(repo
+        .create_tag("v0.1.0", SECOND_COMMIT_DIGEST, None, timestamp)
+        .is_err());
+
+    let tags: Vec<(String, String)> = repo
+        .tags()
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.name, t.target))
+        .collect();
+    assert_eq!(
+        tags,
+        vec![
+            (String::from("v0.1.0"), String::from(FIRST_COMMIT_DIGEST)),
+            (String::from("v0.2.0"), String::from(SECOND_COMMIT_DIGEST)),
+        ]
+    );
+    assert_eq!(repo.resolve_rev("v0.1.0").unwrap(), FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("v0.2.0~1").unwrap(), FIRST_COMMIT_DIGEST);
+
+    repo.delete_tag("v0.2.0").unwrap();
+    assert!(repo.resolve_rev("v0.2.0").is_err());
+    assert!(repo.delete_tag("v0.2.0").is_err());
+
+    // Inspect the second commit.
+    let details = repo
+        .show(SECOND_COMMIT_DIGEST, true)
+        .expect("showing commit failed");
+    assert_eq!(details.parents, vec![FIRST_COMMIT_DIGEST]);
+    assert_eq!(details.author, "Vitalii Shvedchenko");
+    assert_eq!(details.timestamp, 1680961869);
+    assert_eq!(details.message, "second commit descriptive message");
+    let changes: Vec<(get::ChangeKind, PathBuf)> = details
+        .changes
+        .into_iter()
+        .map(|c| (c.kind, c.path))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (get::ChangeKind::Modified, PathBuf::from("test_file.txt")),
+            (
+                get::ChangeKind::Added,
+                PathBuf::from("testdir/new_name.txt")
+            ),
+            (
+                get::ChangeKind::Deleted,
+                PathBuf::from("testdir/test_file1.txt")
+            ),
+        ]
+    );
+    let patch = details.patch.expect("patch was requested");
+    assert!(patch.contains("--- a/test_file.txt\n+++ b/test_file.txt\n"));
+    assert!(patch.contains("-thats\n-all,\n-folks!\n+and now it is modified!\n"));
+
+    let first_details = repo.show(FIRST_COMMIT_DIGEST, false).unwrap();
+    assert!(first_details.parents.is_empty());
+    assert_eq!(first_details.changes.len(), 5);
+    assert!(first_details.patch.is_none());
+
     // Restore the first commit.
-    assert!(repo.restore(FIRST_COMMIT_DIGEST).is_ok());
+    assert_eq!(
+        repo.restore(FIRST_COMMIT_DIGEST).unwrap(),
+        get::RestoreResult {
+            commit: FIRST_COMMIT_DIGEST.to_owned()
+        }
+    );
 
     // Check commit digest was updated into HEAD after restore a previous commit.
     let cur_head = fs::read_to_string(repo_root.path().join(".get/HEAD"));
@@ -88,9 +228,1342 @@ fn repo_workflow() {
     let cur_head = fs::read_to_string(repo_root.path().join(".get/HEAD"));
     assert!(cur_head.is_ok());
     assert_eq!(cur_head.unwrap(), SECOND_COMMIT_DIGEST,);
+
+    // Every HEAD movement is recorded in the log, the most recent one first.
+    let reflog = repo.reflog().expect("reading reflog failed");
+    let movements: Vec<(&str, &str, &str)> = reflog
+        .iter()
+        .map(|e| (e.old.as_str(), e.new.as_str(), e.operation.as_str()))
+        .collect();
+    assert_eq!(
+        movements,
+        vec![
+            (FIRST_COMMIT_DIGEST, SECOND_COMMIT_DIGEST, "restore"),
+            (SECOND_COMMIT_DIGEST, FIRST_COMMIT_DIGEST, "restore"),
+            (FIRST_COMMIT_DIGEST, SECOND_COMMIT_DIGEST, "commit"),
+            (
+                "0000000000000000000000000000000000000000",
+                FIRST_COMMIT_DIGEST,
+                "commit"
+            ),
+        ]
+    );
+    assert_eq!(reflog[3].author, "Vitalii Shvedchenko");
+    assert_eq!(reflog[3].timestamp, 1680961369);
+    assert_eq!(reflog[3].message, "descriptive message");
+
+    // Undo the second commit keeping the files.
+    let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1680962369);
+    assert_eq!(
+        repo.uncommit(false, timestamp).unwrap(),
+        FIRST_COMMIT_DIGEST
+    );
+    let cur_head = fs::read_to_string(repo_root.path().join(".get/HEAD")).unwrap();
+    assert_eq!(cur_head, FIRST_COMMIT_DIGEST);
+    assert_eq!(after_changes, working_files_snapshot(&working_dir));
+    assert_eq!(repo.reflog().unwrap()[0].operation, "uncommit");
+
+    // There is nothing before the first commit.
+    assert!(repo.uncommit(true, timestamp).is_err());
+
+    // Undone commit is still reachable through the reflog.
+    assert_eq!(repo.resolve_rev("@{1}").unwrap(), SECOND_COMMIT_DIGEST);
+
+    // Amend the first commit with the changes still in the working directory, keeping its
+    // message and its (absent) parent.
+    let amended = repo.amend(None, timestamp).expect("amend failed");
+    assert_ne!(amended, FIRST_COMMIT_DIGEST);
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), amended);
+    let details = repo.show(&amended, false).unwrap();
+    assert!(details.parents.is_empty());
+    assert_eq!(details.message, "descriptive message");
+    assert_eq!(details.changes.len(), 5);
+    assert_eq!(repo.reflog().unwrap()[0].operation, "commit (amend)");
+
+    let reworded = repo.amend(Some("reworded"), timestamp).unwrap();
+    assert_eq!(repo.show(&reworded, false).unwrap().message, "reworded");
+}
+
+#[test]
+fn merge_workflow() {
+    let repo_root = TempDir::new("get_merge_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+
+    // One side changes the first line of a file and adds a new file.
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!").unwrap();
+    fs::write(working_dir.join("testdir").join("ours.txt"), "ours").unwrap();
+    let ours = repo.commit(Some("ours"), at(1680961469)).unwrap();
+
+    // The other side changes the last line of the same file and removes another one.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks!!!").unwrap();
+    fs::remove_file(working_dir.join("testdir").join("test_file2.txt")).unwrap();
+    let theirs = repo.commit(Some("theirs"), at(1680961569)).unwrap();
+
+    assert_eq!(repo.merge_base(&ours, &theirs).unwrap(), Some(base.clone()));
+
+    // Merging refuses to overwrite uncommitted changes.
+    fs::write(working_dir.join("dirty.txt"), "dirty").unwrap();
+    assert!(repo.merge(&ours, at(1680961669)).is_err());
+    fs::remove_file(working_dir.join("dirty.txt")).unwrap();
+
+    // Non overlapping changes are merged automatically.
+    let merge_commit = match repo.merge(&ours, at(1680961669)).expect("merge failed") {
+        get::MergeOutcome::Merged { base: b, commit } => {
+            assert_eq!(b, Some(base.clone()));
+            commit
+        }
+        outcome => panic!("merge commit expected, got {:?}", outcome),
+    };
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), merge_commit);
+    assert_eq!(
+        repo.show(&merge_commit, false).unwrap().parents,
+        vec![theirs.clone(), ours.clone()]
+    );
+    assert_eq!(repo.resolve_rev("HEAD^2").unwrap(), ours);
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\nfolks!!!"
+    );
+    assert!(working_dir.join("testdir").join("ours.txt").is_file());
+    assert!(!working_dir.join("testdir").join("test_file2.txt").exists());
+    let after_merge = working_files_snapshot(&working_dir);
+
+    // Merging it again changes nothing.
+    assert_eq!(
+        repo.merge(&ours, at(1680961669)).unwrap(),
+        get::MergeOutcome::UpToDate
+    );
+
+    // Merging into an ancestor just moves HEAD forward.
+    repo.restore(&ours).unwrap();
+    assert_eq!(
+        repo.merge(&merge_commit, at(1680961669)).unwrap(),
+        get::MergeOutcome::FastForward(merge_commit.clone())
+    );
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), merge_commit);
+    assert_eq!(after_merge, working_files_snapshot(&working_dir));
+
+    // Overlapping changes leave conflict markers to resolve.
+    repo.restore(&base).unwrap();
+    fs::write(
+        working_dir.join("test_file.txt"),
+        "thats\neverything,\nfolks!",
+    )
+    .unwrap();
+    let left = repo.commit(Some("left"), at(1680961769)).unwrap();
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "thats\nnothing,\nfolks!").unwrap();
+    let right = repo.commit(Some("right"), at(1680961869)).unwrap();
+
+    let before_merge = working_files_snapshot(&working_dir);
+    let conflicts = get::MergeOutcome::Conflicts {
+        base: Some(base.clone()),
+        conflicts: vec![PathBuf::from("test_file.txt")],
+    };
+    assert_eq!(repo.merge(&left, at(1680961969)).unwrap(), conflicts);
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        format!("thats\n<<<<<<< HEAD\nnothing,\n=======\neverything,\n>>>>>>> {left}\nfolks!")
+    );
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), right);
+    assert!(working_dir.join(".get/MERGE_HEAD").is_file());
+
+    // Nothing can be committed until conflicts are resolved and no other merge can be started.
+    assert!(repo.merge_continue(None, at(1680962069)).is_err());
+    assert!(repo.commit(None, at(1680962069)).is_err());
+    assert!(matches!(
+        repo.amend(None, at(1680962069)),
+        Err(get::error::Error::MergeInProgress("merge"))
+    ));
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), right);
+    assert!(repo.merge(&base, at(1680962069)).is_err());
+
+    // Abort brings back the files as they were before the merge.
+    repo.merge_abort().unwrap();
+    assert_eq!(before_merge, working_files_snapshot(&working_dir));
+    assert!(!working_dir.join(".get/MERGE_HEAD").exists());
+    assert!(repo.merge_abort().is_err());
+
+    // Continuing with resolved conflicts concludes the merge.
+    assert_eq!(repo.merge(&left, at(1680961969)).unwrap(), conflicts);
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks!").unwrap();
+    let resolved = repo.merge_continue(None, at(1680962069)).unwrap();
+    assert_eq!(
+        repo.show(&resolved, false).unwrap().message,
+        format!("Merge commit {left}")
+    );
+    assert_eq!(
+        repo.show(&resolved, false).unwrap().parents,
+        vec![right.clone(), left.clone()]
+    );
+    let next = repo.commit(Some("next"), at(1680962169)).unwrap();
+    assert_eq!(repo.show(&next, false).unwrap().parents, vec![resolved]);
+}
+
+#[test]
+fn cherry_pick_workflow() {
+    let repo_root = TempDir::new("get_cherry_pick_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+
+    // A side commit changes the last line of a file and adds a new file.
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks!!!").unwrap();
+    fs::write(working_dir.join("picked.txt"), "picked").unwrap();
+    let side = repo.commit(Some("side"), at(1680961469)).unwrap();
+
+    // Main line changes the first line.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!").unwrap();
+    let main = repo.commit(Some("main"), at(1680961569)).unwrap();
+
+    let picked = match repo.cherry_pick(&side, at(1680961669)).unwrap() {
+        get::MergeOutcome::Merged { base: b, commit } => {
+            assert_eq!(b, Some(base.clone()));
+            commit
+        }
+        outcome => panic!("commit expected, got {:?}", outcome),
+    };
+    let details = repo.show(&picked, false).unwrap();
+    assert_eq!(details.parents, vec![main.clone()]);
+    assert_eq!(details.message, "side");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\nfolks!!!"
+    );
+    assert_eq!(
+        fs::read_to_string(working_dir.join("picked.txt")).unwrap(),
+        "picked"
+    );
+
+    // Revert undoes only the changes of the reverted commit.
+    let reverted = match repo.revert(&main, at(1680961769)).unwrap() {
+        get::MergeOutcome::Merged { base: b, commit } => {
+            assert_eq!(b, Some(main.clone()));
+            commit
+        }
+        outcome => panic!("commit expected, got {:?}", outcome),
+    };
+    let details = repo.show(&reverted, false).unwrap();
+    assert_eq!(details.parents, vec![picked.clone()]);
+    assert_eq!(details.message, format!("Revert commit {main}"));
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "thats\nall,\nfolks!!!"
+    );
+    assert!(working_dir.join("picked.txt").is_file());
+
+    // Reverting side after the last line is changed again overlaps with that change.
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks?").unwrap();
+    let changed = repo.commit(Some("changed"), at(1680961869)).unwrap();
+    let before = working_files_snapshot(&working_dir);
+
+    let outcome = repo.revert(&side, at(1680961969)).unwrap();
+    assert_eq!(
+        outcome,
+        get::MergeOutcome::Conflicts {
+            base: Some(side.clone()),
+            conflicts: vec![PathBuf::from("test_file.txt")],
+        }
+    );
+    assert!(working_dir.join(".get/REVERT_HEAD").is_file());
+    assert!(!working_dir.join("picked.txt").exists());
+    assert!(repo.cherry_pick(&side, at(1680961969)).is_err());
+    assert!(repo.merge(&side, at(1680961969)).is_err());
+
+    repo.merge_abort().unwrap();
+    assert_eq!(before, working_files_snapshot(&working_dir));
+    assert!(!working_dir.join(".get/REVERT_HEAD").exists());
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), changed);
+
+    // Continuing makes a regular commit with the saved message.
+    let _ = repo.revert(&side, at(1680961969)).unwrap();
+    assert!(repo.merge_continue(None, at(1680962069)).is_err());
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks!").unwrap();
+    let resolved = repo.merge_continue(None, at(1680962069)).unwrap();
+    let details = repo.show(&resolved, false).unwrap();
+    assert_eq!(details.parents, vec![changed]);
+    assert_eq!(details.message, format!("Revert commit {side}"));
+    assert_eq!(repo.reflog().unwrap()[0].operation, "commit (revert)");
+}
+
+#[test]
+fn modify_delete_conflicts() {
+    let repo_root = TempDir::new("get_modify_delete_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(working_dir.join("file.txt"), "one\n").unwrap();
+    fs::write(working_dir.join("other.txt"), "other\n").unwrap();
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+
+    fs::remove_file(working_dir.join("file.txt")).unwrap();
+    let deleted = repo.commit(Some("delete"), at(1680961469)).unwrap();
+
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("file.txt"), "two\n").unwrap();
+    let changed = repo.commit(Some("change"), at(1680961569)).unwrap();
+
+    // Changed version is kept between markers, continuing waits until it is resolved.
+    let conflicts = match repo.merge(&deleted, at(1680961669)).unwrap() {
+        get::MergeOutcome::Conflicts { conflicts, .. } => conflicts,
+        outcome => panic!("conflicts expected, got {:?}", outcome),
+    };
+    assert_eq!(conflicts, vec![PathBuf::from("file.txt")]);
+    assert_eq!(
+        fs::read_to_string(working_dir.join("file.txt")).unwrap(),
+        format!("<<<<<<< HEAD\ntwo\n=======\n>>>>>>> {deleted}\n")
+    );
+    assert!(matches!(
+        repo.merge_continue(None, at(1680961769)),
+        Err(get::error::Error::UnresolvedConflicts(paths)) if paths == conflicts
+    ));
+
+    // Removing the file resolves the conflict in favor of the deletion.
+    fs::remove_file(working_dir.join("file.txt")).unwrap();
+    repo.merge_continue(None, at(1680961769)).unwrap();
+    assert_eq!(repo.status().unwrap(), vec![]);
+    assert!(!working_dir.join("file.txt").exists());
+
+    // The same goes for cherry-pick, here the file is kept by removing the markers.
+    assert!(matches!(
+        repo.cherry_pick(&changed, at(1680961869)).unwrap(),
+        get::MergeOutcome::Conflicts { .. }
+    ));
+    assert!(matches!(
+        repo.merge_continue(None, at(1680961969)),
+        Err(get::error::Error::UnresolvedConflicts(_))
+    ));
+    fs::write(working_dir.join("file.txt"), "two\n").unwrap();
+    repo.merge_continue(None, at(1680961969)).unwrap();
+    assert_eq!(repo.status().unwrap(), vec![]);
+
+    // And for revert of a change to a file deleted since.
+    fs::remove_file(working_dir.join("file.txt")).unwrap();
+    repo.commit(Some("drop"), at(1680962069)).unwrap();
+    assert!(matches!(
+        repo.revert(&changed, at(1680962169)).unwrap(),
+        get::MergeOutcome::Conflicts { .. }
+    ));
+    assert!(matches!(
+        repo.merge_continue(None, at(1680962269)),
+        Err(get::error::Error::UnresolvedConflicts(_))
+    ));
+    fs::remove_file(working_dir.join("file.txt")).unwrap();
+    repo.merge_continue(None, at(1680962269)).unwrap();
+    assert_eq!(repo.status().unwrap(), vec![]);
+}
+
+#[test]
+fn rebase_workflow() {
+    let repo_root = TempDir::new("get_rebase_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+
+    // Two commits on one line of history.
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks!!!").unwrap();
+    let first = repo.commit(Some("first"), at(1680961469)).unwrap();
+    fs::write(working_dir.join("new.txt"), "new").unwrap();
+    let second = repo.commit(Some("second"), at(1680961569)).unwrap();
+
+    // Upstream changes the first line of the same file.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!").unwrap();
+    let upstream = repo.commit(Some("upstream"), at(1680961669)).unwrap();
+
+    // Rebase is done by someone else, but replayed commits keep their metadata.
+    repo.restore(&second).unwrap();
+    fs::write(
+        working_dir.join(".get.toml"),
+        "ignore = [\".idea\"]\nauthor = \"Someone Else\"\n",
+    )
+    .unwrap();
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let tip = match repo.rebase(&upstream, at(1680961769)).unwrap() {
+        get::RebaseOutcome::Rebased(tip) => tip,
+        outcome => panic!("rebase expected, got {:?}", outcome),
+    };
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), tip);
+    assert_eq!(repo.resolve_rev("HEAD~2").unwrap(), upstream);
+    let details = repo.show(&tip, false).unwrap();
+    assert_eq!(details.author, "Vitalii Shvedchenko");
+    assert_eq!(details.timestamp, 1680961569);
+    assert_eq!(details.message, "second");
+    let replayed_first = repo.resolve_rev("HEAD~1").unwrap();
+    assert_eq!(repo.show(&replayed_first, false).unwrap().message, "first");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\nfolks!!!"
+    );
+    assert!(working_dir.join("new.txt").is_file());
+
+    // Reflog goes from the old tip to the new one, so the rebase can be undone.
+    let entry = &repo.reflog().unwrap()[0];
+    assert_eq!(entry.operation, "rebase (finish)");
+    assert_eq!(entry.old, second);
+    assert_eq!(entry.new, tip);
+
+    assert_eq!(
+        repo.rebase(&upstream, at(1680961869)).unwrap(),
+        get::RebaseOutcome::UpToDate
+    );
+
+    repo.restore(&base).unwrap();
+    assert_eq!(
+        repo.rebase(&upstream, at(1680961869)).unwrap(),
+        get::RebaseOutcome::FastForward(upstream.clone())
+    );
+
+    // Upstream changes the same line as the first commit.
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks?").unwrap();
+    let conflicting = repo.commit(Some("conflicting"), at(1680961969)).unwrap();
+
+    repo.restore(&second).unwrap();
+    let before = working_files_snapshot(&working_dir);
+    let conflicts = get::RebaseOutcome::Conflicts {
+        commit: first.clone(),
+        conflicts: vec![PathBuf::from("test_file.txt")],
+    };
+    assert_eq!(
+        repo.rebase(&conflicting, at(1680962069)).unwrap(),
+        conflicts
+    );
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), conflicting);
+    assert!(repo.commit(None, at(1680962069)).is_err());
+    assert!(matches!(
+        repo.amend(None, at(1680962069)),
+        Err(get::error::Error::MergeInProgress("rebase"))
+    ));
+    assert!(repo.merge(&base, at(1680962069)).is_err());
+    assert!(repo.rebase_continue(at(1680962069)).is_err());
+
+    assert_eq!(repo.rebase_abort(at(1680962069)).unwrap(), second);
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), second);
+    assert_eq!(before, working_files_snapshot(&working_dir));
+    assert!(repo.rebase_skip(at(1680962069)).is_err());
+
+    // Skipping drops the conflicting commit.
+    assert_eq!(
+        repo.rebase(&conflicting, at(1680962069)).unwrap(),
+        conflicts
+    );
+    let tip = match repo.rebase_skip(at(1680962169)).unwrap() {
+        get::RebaseOutcome::Rebased(tip) => tip,
+        outcome => panic!("rebase expected, got {:?}", outcome),
+    };
+    assert_eq!(repo.resolve_rev("HEAD~1").unwrap(), conflicting);
+    assert_eq!(repo.show(&tip, false).unwrap().message, "second");
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\nfolks?"
+    );
+
+    // Continuing commits the resolution with the metadata of the stopped commit.
+    repo.restore(&second).unwrap();
+    assert_eq!(
+        repo.rebase(&conflicting, at(1680962269)).unwrap(),
+        conflicts
+    );
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks?!").unwrap();
+    let tip = match repo.rebase_continue(at(1680962369)).unwrap() {
+        get::RebaseOutcome::Rebased(tip) => tip,
+        outcome => panic!("rebase expected, got {:?}", outcome),
+    };
+    assert_eq!(repo.resolve_rev("HEAD~2").unwrap(), conflicting);
+    let resolved = repo.resolve_rev(&format!("{tip}~1")).unwrap();
+    let details = repo.show(&resolved, false).unwrap();
+    assert_eq!(details.message, "first");
+    assert_eq!(details.timestamp, 1680961469);
+    assert!(!working_dir.join(".get/rebase").exists());
+}
+
+#[test]
+fn stash_workflow() {
+    let repo_root = TempDir::new("get_stash_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+    let clean = working_files_snapshot(&working_dir);
+
+    assert!(repo.stash_push(None, at(1680961469)).is_err());
+
+    // Both changed and new files are stashed.
+    fs::write(working_dir.join("test_file.txt"), "thats\nall,\nfolks?").unwrap();
+    fs::write(working_dir.join("wip.txt"), "wip").unwrap();
+    let entry = repo.stash_push(Some("wip"), at(1680961469)).unwrap();
+    assert_eq!(entry.head, base);
+    assert_eq!(entry.message, format!("On {base}: wip"));
+    assert_eq!(clean, working_files_snapshot(&working_dir));
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), base);
+    assert_eq!(repo.stash_list().unwrap(), vec![entry.clone()]);
+
+    // Stash is merged with the changes committed since.
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!").unwrap();
+    let head = repo.commit(Some("first line"), at(1680961569)).unwrap();
+    let committed = working_files_snapshot(&working_dir);
+
+    assert!(repo.stash_apply(1).is_err());
+    assert_eq!(repo.stash_apply(0).unwrap(), Vec::<PathBuf>::new());
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\nfolks?"
+    );
+    assert_eq!(
+        fs::read_to_string(working_dir.join("wip.txt")).unwrap(),
+        "wip"
+    );
+    assert_eq!(repo.stash_list().unwrap().len(), 1);
+
+    // Applying refuses to overwrite uncommitted changes.
+    assert!(repo.stash_apply(0).is_err());
+
+    repo.restore(&head).unwrap();
+    assert_eq!(committed, working_files_snapshot(&working_dir));
+    assert_eq!(repo.stash_pop(0).unwrap(), Vec::<PathBuf>::new());
+    assert!(working_dir.join("wip.txt").is_file());
+    assert!(repo.stash_list().unwrap().is_empty());
+
+    // Conflicting stash is applied with markers and kept.
+    repo.restore(&head).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks?").unwrap();
+    let _ = repo.stash_push(None, at(1680961669)).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!!!").unwrap();
+    let _ = repo.commit(Some("last line"), at(1680961769)).unwrap();
+    assert_eq!(
+        repo.stash_pop(0).unwrap(),
+        vec![PathBuf::from("test_file.txt")]
+    );
+    assert_eq!(
+        fs::read_to_string(working_dir.join("test_file.txt")).unwrap(),
+        "that's\nall,\n<<<<<<< HEAD\nfolks!!!\n=======\nfolks?\n>>>>>>> stash@{0}\n"
+    );
+    let entries = repo.stash_list().unwrap();
+    assert_eq!(entries[0].message, format!("WIP on {head}"));
+
+    assert_eq!(repo.stash_drop(0).unwrap(), entries[0]);
+    assert!(repo.stash_list().unwrap().is_empty());
+    assert!(repo.stash_drop(0).is_err());
+}
+
+#[test]
+fn blame_workflow() {
+    let repo_root = TempDir::new("get_blame_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    let first = repo.commit(Some("first"), at(1680961369)).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "that's\nall,\nfolks!").unwrap();
+    let second = repo.commit(Some("second"), at(1680961469)).unwrap();
+    // Commit which doesn't touch the file.
+    fs::write(working_dir.join("other.txt"), "other").unwrap();
+    let _ = repo.commit(Some("third"), at(1680961569)).unwrap();
+    fs::write(
+        working_dir.join("test_file.txt"),
+        "that's\nnot\nall,\nfolks!",
+    )
+    .unwrap();
+    let fourth = repo.commit(Some("fourth"), at(1680961669)).unwrap();
+
+    let blamed = repo.blame(Path::new("test_file.txt"), &fourth).unwrap();
+    let origins: Vec<(&str, u64, usize, &str)> = blamed
+        .iter()
+        .map(|l| {
+            (
+                l.commit.as_str(),
+                l.timestamp,
+                l.line_number,
+                l.content.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        origins,
+        vec![
+            (second.as_str(), 1680961469, 1, "that's"),
+            (fourth.as_str(), 1680961669, 2, "not"),
+            (first.as_str(), 1680961369, 3, "all,"),
+            (first.as_str(), 1680961369, 4, "folks!"),
+        ]
+    );
+    assert_eq!(blamed[0].author, "Vitalii Shvedchenko");
+
+    // Absolute paths work too and older commits are blamed as they were.
+    let blamed = repo
+        .blame(&working_dir.join("test_file.txt"), &second)
+        .unwrap();
+    assert_eq!(blamed.len(), 3);
+    assert_eq!(blamed[0].commit, second);
+
+    assert!(repo.blame(Path::new("other.txt"), &second).is_err());
+}
+
+#[test]
+fn log_workflow() {
+    let repo_root = TempDir::new("get_log_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let testdir = working_dir.join("testdir");
+
+    let first = repo.commit(Some("first"), at(1680961369)).unwrap();
+    fs::write(testdir.join("test_file1.txt"), "changed").unwrap();
+    let second = repo.commit(Some("second"), at(1680961469)).unwrap();
+    fs::write(working_dir.join("test_file.txt"), "changed").unwrap();
+    let third = repo.commit(Some("third"), at(1680961569)).unwrap();
+    fs::rename(testdir.join("test_file1.txt"), testdir.join("renamed.txt")).unwrap();
+    let fourth = repo.commit(Some("fourth"), at(1680961669)).unwrap();
+    fs::write(testdir.join("renamed.txt"), "changed again").unwrap();
+    let fifth = repo.commit(Some("fifth"), at(1680961769)).unwrap();
+
+    let log = |path: Option<&str>, follow: bool| -> Vec<String> {
+        let options = get::LogOptions {
+            path: path.map(PathBuf::from),
+            follow,
+            ..Default::default()
+        };
+        repo.log(&fifth, options)
+            .unwrap()
+            .map(|c| c.unwrap().digest)
+            .collect()
+    };
+
+    assert_eq!(
+        log(None, false),
+        vec![
+            fifth.clone(),
+            fourth.clone(),
+            third.clone(),
+            second.clone(),
+            first.clone()
+        ]
+    );
+    let details = repo.log(&fifth, get::LogOptions::default()).unwrap().next();
+    assert_eq!(details.unwrap().unwrap().message, "fifth");
+
+    assert_eq!(
+        log(Some("test_file.txt"), false),
+        vec![third.clone(), first.clone()]
+    );
+    assert_eq!(
+        log(Some("testdir"), false),
+        vec![fifth.clone(), fourth.clone(), second.clone(), first.clone()]
+    );
+    assert_eq!(
+        log(Some("testdir/renamed.txt"), false),
+        vec![fifth.clone(), fourth.clone()]
+    );
+    assert_eq!(
+        log(Some("testdir/renamed.txt"), true),
+        vec![fifth.clone(), fourth.clone(), second.clone(), first.clone()]
+    );
+    assert!(log(Some("missing.txt"), false).is_empty());
+
+    // Absolute paths are taken relative to the working directory.
+    let options = get::LogOptions {
+        path: Some(working_dir.join("test_file.txt")),
+        ..Default::default()
+    };
+    assert_eq!(repo.log(&fifth, options).unwrap().count(), 2);
+
+    // Working directory root is no filter at all.
+    let options = get::LogOptions {
+        path: Some(working_dir.join(".")),
+        ..Default::default()
+    };
+    assert_eq!(repo.log(&fifth, options).unwrap().count(), 5);
+
+    let search = |options: get::LogOptions| -> Vec<String> {
+        repo.log(&fifth, options)
+            .unwrap()
+            .map(|c| c.unwrap().digest)
+            .collect()
+    };
+
+    let options = get::LogOptions {
+        grep: Some(String::from("^f(ir|if)")),
+        ..Default::default()
+    };
+    assert_eq!(search(options), vec![fifth.clone(), first.clone()]);
+
+    let options = get::LogOptions {
+        author: Some(String::from("Shvedchenko$")),
+        since: Some(String::from("2023-04-08 13:46:09")),
+        until: Some(String::from("1680961669")),
+        ..Default::default()
+    };
+    assert_eq!(search(options), vec![fourth.clone(), third.clone()]);
+
+    let options = get::LogOptions {
+        author: Some(String::from("Someone Else")),
+        ..Default::default()
+    };
+    assert!(search(options).is_empty());
+
+    // Filters are combined with the path.
+    let options = get::LogOptions {
+        path: Some(PathBuf::from("testdir/renamed.txt")),
+        follow: true,
+        until: Some(String::from("2023-04-08 13:46:00")),
+        ..Default::default()
+    };
+    assert_eq!(search(options), vec![second.clone(), first.clone()]);
+
+    let options = get::LogOptions {
+        grep: Some(String::from("(")),
+        ..Default::default()
+    };
+    assert!(repo.log(&fifth, options).is_err());
+    let options = get::LogOptions {
+        since: Some(String::from("last week")),
+        ..Default::default()
+    };
+    assert!(repo.log(&fifth, options).is_err());
+}
+
+#[test]
+fn bisect_workflow() {
+    let repo_root = TempDir::new("get_bisect_test").unwrap();
+    let mut working_dir = repo_root.path().to_owned();
+
+    setup_project_dir(&mut working_dir);
+    let _ = get::Repo::init(&mut working_dir).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    // The bug is introduced by the fourth commit out of seven.
+    let mut commits = Vec::new();
+    for i in 0..7u64 {
+        let content = if i >= 3 { "bug" } else { "fine" };
+        fs::write(working_dir.join("test_file.txt"), format!("{content}\n{i}")).unwrap();
+        commits.push(repo.commit(Some("step"), at(1680961369 + i * 100)).unwrap());
+    }
+    let head = commits[6].clone();
+
+    assert!(repo.bisect_good(&commits[0]).is_err());
+    assert_eq!(
+        repo.bisect_start(None, &[]).unwrap(),
+        get::BisectOutcome::Waiting
+    );
+    assert!(repo.bisect_start(None, &[]).is_err());
+    assert_eq!(repo.bisect_bad(&head).unwrap(), get::BisectOutcome::Waiting);
+
+    // Marks are saved between invocations, so every step uses a freshly opened repo.
+    let mut outcome = repo.bisect_good(&commits[0]).unwrap();
+    let mut steps = 0;
+    while let get::BisectOutcome::Testing { commit, .. } = outcome {
+        steps += 1;
+        let repo = get::Repo::try_from(&working_dir).unwrap();
+        assert_eq!(repo.resolve_rev("HEAD").unwrap(), commit);
+
+        let content = fs::read_to_string(working_dir.join("test_file.txt")).unwrap();
+        outcome = if content.starts_with("bug") {
+            repo.bisect_bad(&commit).unwrap()
+        } else {
+            repo.bisect_good(&commit).unwrap()
+        };
+    }
+    assert_eq!(outcome, get::BisectOutcome::Found(commits[3].clone()));
+    assert!(steps <= 3);
+
+    assert_eq!(repo.bisect_reset().unwrap(), head);
+    assert_eq!(repo.resolve_rev("HEAD").unwrap(), head);
+    assert!(!working_dir.join(".get/bisect").exists());
+    assert!(repo.bisect_reset().is_err());
+
+    // Command exit codes mark the commits.
+    let _ = repo
+        .bisect_start(Some(&head), &[commits[0].as_str()])
+        .unwrap();
+    let command = ["sh", "-c", "grep -q fine test_file.txt"].map(String::from);
+    assert_eq!(
+        repo.bisect_run(&command).unwrap(),
+        get::BisectOutcome::Found(commits[3].clone())
+    );
+    let _ = repo.bisect_reset().unwrap();
+
+    // Skipping every candidate leaves the search inconclusive.
+    let mut outcome = repo
+        .bisect_start(Some(&commits[4]), &[commits[1].as_str()])
+        .unwrap();
+    while let get::BisectOutcome::Testing { commit, .. } = outcome {
+        outcome = repo.bisect_skip(&commit).unwrap();
+    }
+    let mut expected = commits[2..5].to_vec();
+    expected.sort();
+    assert_eq!(outcome, get::BisectOutcome::Inconclusive(expected));
+    let _ = repo.bisect_reset().unwrap();
+
+    // Bad commit older than a good one is most likely a mix-up.
+    assert!(matches!(
+        repo.bisect_start(Some(&commits[1]), &[commits[4].as_str()]),
+        Err(get::error::Error::BisectBadIsAncestorOfGood(bad)) if bad == commits[1]
+    ));
+    let _ = repo.bisect_reset().unwrap();
+}
+
+#[test]
+fn ignore_patterns() {
+    let repo_root = TempDir::new("get_ignore_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(
+        working_dir.join(".get.toml"),
+        "ignore = [\"*.log\", \"!keep.log\", \"/build\", \"docs/**/*.tmp\"]\n",
+    )
+    .unwrap();
+    for dir in ["build", "src/build", "docs/a/b"] {
+        fs::create_dir_all(working_dir.join(dir)).unwrap();
+    }
+    for file in [
+        "debug.log",
+        "keep.log",
+        "build/out.o",
+        "src/build/out.o",
+        "src/main.rs",
+        "docs/a/b/draft.tmp",
+        "docs/a/b/page.md",
+    ] {
+        fs::write(working_dir.join(file), file).unwrap();
+    }
+
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let commit = repo.commit(Some("first"), at(1680961369)).unwrap();
+
+    let files: Vec<PathBuf> = repo
+        .ls_tree(&commit, true)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+    assert_eq!(
+        files,
+        [
+            "docs/a/b/page.md",
+            "keep.log",
+            "src/build/out.o",
+            "src/main.rs"
+        ]
+        .map(PathBuf::from)
+    );
+
+    // Ignored files survive restore.
+    fs::write(working_dir.join("src/main.rs"), "changed").unwrap();
+    repo.restore(&commit).unwrap();
+    assert_eq!(
+        fs::read_to_string(working_dir.join("src/main.rs")).unwrap(),
+        "src/main.rs"
+    );
+    for file in ["debug.log", "build/out.o", "docs/a/b/draft.tmp"] {
+        assert!(working_dir.join(file).is_file());
+    }
+}
+
+#[test]
+fn nested_ignore_files() {
+    let repo_root = TempDir::new("get_nested_ignore_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(working_dir.join(".get.toml"), "ignore = [\"*.log\"]\n").unwrap();
+    for dir in ["sub/out", "out", "skipped"] {
+        fs::create_dir_all(working_dir.join(dir)).unwrap();
+    }
+    for file in [
+        "debug.log",
+        "out/a.txt",
+        "sub/out/a.txt",
+        "sub/keep.log",
+        "skipped/b.txt",
+    ] {
+        fs::write(working_dir.join(file), file).unwrap();
+    }
+    fs::write(working_dir.join(".getignore"), "skipped/\n").unwrap();
+    fs::write(
+        working_dir.join("sub/.getignore"),
+        "# build output\n/out\n!keep.log\n",
+    )
+    .unwrap();
+    // Never read, the directory is ignored.
+    fs::write(working_dir.join("skipped/.getignore"), "!b.txt\n").unwrap();
+
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let commit = repo.commit(Some("first"), at(1680961369)).unwrap();
+
+    let files: Vec<PathBuf> = repo
+        .ls_tree(&commit, true)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.path)
+        .collect();
+    assert_eq!(
+        files,
+        [".getignore", "out/a.txt", "sub/.getignore", "sub/keep.log"].map(PathBuf::from)
+    );
+
+    let found = repo
+        .check_ignore(&working_dir.join("sub/out/a.txt"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.to_string(), "sub/.getignore:2:/out");
+
+    let found = repo.check_ignore(Path::new("debug.log")).unwrap().unwrap();
+    assert_eq!(found.to_string(), ".get.toml:1:*.log");
+
+    let found = repo.check_ignore(Path::new(".get/HEAD")).unwrap().unwrap();
+    assert_eq!(found.to_string(), "<built-in>:1:.get");
+
+    assert_eq!(repo.check_ignore(Path::new("sub/keep.log")).unwrap(), None);
+
+    // Repo config is found from a subdirectory as well.
+    let repo = get::Repo::try_from(&working_dir.join("sub")).unwrap();
+    let found = repo.check_ignore(Path::new("debug.log")).unwrap().unwrap();
+    assert_eq!(found.to_string(), ".get.toml:1:*.log");
+}
+
+#[test]
+fn clean_workflow() {
+    let repo_root = TempDir::new("get_clean_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(working_dir.join(".get.toml"), "ignore = [\"*.log\"]\n").unwrap();
+    fs::create_dir_all(working_dir.join("src")).unwrap();
+    fs::write(working_dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    repo.commit(Some("first"), at(1680961369)).unwrap();
+    assert_eq!(repo.status().unwrap(), vec![]);
+
+    fs::create_dir_all(working_dir.join("scratch/deep")).unwrap();
+    for file in ["src/new.rs", "src/debug.log", "scratch/deep/notes.txt"] {
+        fs::write(working_dir.join(file), file).unwrap();
+    }
+    fs::write(working_dir.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+
+    let status: Vec<(get::ChangeKind, PathBuf)> = repo
+        .status()
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.kind, c.path))
+        .collect();
+    assert_eq!(
+        status,
+        vec![
+            (
+                get::ChangeKind::Added,
+                PathBuf::from("scratch/deep/notes.txt")
+            ),
+            (get::ChangeKind::Modified, PathBuf::from("src/main.rs")),
+            (get::ChangeKind::Added, PathBuf::from("src/new.rs")),
+        ]
+    );
+
+    // Dry run lists the files but keeps them.
+    let options = get::CleanOptions {
+        dry_run: true,
+        directories: true,
+        ignored: true,
+    };
+    assert_eq!(
+        repo.clean(options).unwrap(),
+        ["scratch/", "src/debug.log", "src/new.rs"].map(PathBuf::from)
+    );
+    assert!(working_dir.join("src/debug.log").is_file());
+
+    // Untracked directories and ignored files are kept by default.
+    let removed = repo.clean(get::CleanOptions::default()).unwrap();
+    assert_eq!(removed, [PathBuf::from("src/new.rs")]);
+    assert!(!working_dir.join("src/new.rs").exists());
+    assert!(working_dir.join("scratch/deep/notes.txt").is_file());
+    assert!(working_dir.join("src/debug.log").is_file());
+
+    // Ignored files keep their untracked directory, only the untracked files in it go. Empty
+    // directories go too.
+    fs::create_dir_all(working_dir.join("logs")).unwrap();
+    fs::write(working_dir.join("logs/keep.log"), "keep").unwrap();
+    fs::write(working_dir.join("logs/todo.txt"), "todo").unwrap();
+    fs::create_dir_all(working_dir.join("empty/nested")).unwrap();
+
+    let options = get::CleanOptions {
+        directories: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        repo.clean(options).unwrap(),
+        ["empty/", "logs/todo.txt", "scratch/"].map(PathBuf::from)
+    );
+    assert!(!working_dir.join("scratch").exists());
+    assert!(!working_dir.join("empty").exists());
+    assert!(!working_dir.join("logs/todo.txt").exists());
+    assert!(working_dir.join("logs/keep.log").is_file());
+
+    let options = get::CleanOptions {
+        ignored: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        repo.clean(options).unwrap(),
+        [PathBuf::from("src/debug.log")]
+    );
+
+    // Tracked files and the repo itself are never touched.
+    assert_eq!(
+        fs::read_to_string(working_dir.join("src/main.rs")).unwrap(),
+        "fn main() { todo!() }"
+    );
+    assert!(working_dir.join(".get.toml").is_file());
+    assert!(working_dir.join(".get/HEAD").is_file());
+}
+
+#[test]
+fn config_workflow() {
+    let repo_root = TempDir::new("get_config_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(
+        working_dir.join(".get.toml"),
+        "# Project settings.\nignore = [\"*.log\"] # build noise\nremotes = []\n",
+    )
+    .unwrap();
+    fs::create_dir_all(working_dir.join("src")).unwrap();
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+
+    let subdir = working_dir.join("src");
+    let repo = get::ConfigScope::Repo;
+    get::config_set(&subdir, repo, "author", &[String::from("Jane Doe")]).unwrap();
+    get::config_set(
+        &subdir,
+        repo,
+        "ignore",
+        &[String::from("*.log"), String::from("target")],
+    )
+    .unwrap();
+
+    // Comments and unknown keys are kept.
+    let content = fs::read_to_string(working_dir.join(".get.toml")).unwrap();
+    assert_eq!(
+        content,
+        "# Project settings.\nignore = [\"*.log\", \"target\"] # build noise\nremotes = []\nauthor = \"Jane Doe\"\n"
+    );
+
+    let entries = get::config_entries(&subdir, Some(repo)).unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            get::ConfigEntry {
+                key: String::from("author"),
+                value: get::ConfigValue::String(String::from("Jane Doe")),
+                source: get::ConfigSource::File(PathBuf::from(".get.toml")),
+            },
+            get::ConfigEntry {
+                key: String::from("ignore"),
+                value: get::ConfigValue::List(vec![String::from("*.log"), String::from("target")]),
+                source: get::ConfigSource::File(PathBuf::from(".get.toml")),
+            },
+        ]
+    );
+    let listed: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}\t{} = {}", e.source, e.key, e.value))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ".get.toml\tauthor = \"Jane Doe\"",
+            ".get.toml\tignore = [\"*.log\", \"target\"]"
+        ]
+    );
+
+    let author = get::config_get(&subdir, Some(repo), "author").unwrap();
+    assert_eq!(
+        author[0].value,
+        get::ConfigValue::String(String::from("Jane Doe"))
+    );
+    assert!(get::config_get(&subdir, None, "remotes").is_err());
+
+    // Values are checked against the schema.
+    assert!(get::config_set(&subdir, repo, "author", &[]).is_err());
+    assert!(get::config_set(&subdir, repo, "colour", &[String::from("auto")]).is_err());
+
+    get::config_unset(&subdir, repo, "author").unwrap();
+    get::config_unset(&subdir, repo, "author").unwrap();
+    assert_eq!(
+        get::config_get(&subdir, Some(repo), "author").unwrap(),
+        vec![]
+    );
+
+    // Unknown keys don't stop the repo from opening and can be removed like any other.
+    assert!(get::Repo::try_from(&subdir).is_ok());
+    get::config_unset(&subdir, repo, "remotes").unwrap();
+    assert_eq!(
+        fs::read_to_string(working_dir.join(".get.toml")).unwrap(),
+        "# Project settings.\nignore = [\"*.log\", \"target\"] # build noise\n"
+    );
+
+    // Invalid values are reported with their position.
+    fs::write(
+        working_dir.join(".get.toml"),
+        "# Project settings.\nauthor = 1\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        get::Repo::try_from(&subdir),
+        Err(get::error::Error::ConfigError {
+            line: 2,
+            column: 10,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn authorship_workflow() {
+    let repo_root = TempDir::new("get_authorship_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(
+        working_dir.join(".get.toml"),
+        "author = \"Jane Doe <jane@example.com>\"\n",
+    )
+    .unwrap();
+    fs::write(working_dir.join("file.txt"), "first").unwrap();
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+
+    // Own commits have the same author and committer.
+    let base = repo.commit(Some("base"), at(1680961369)).unwrap();
+    let details = repo.show(&base, false).unwrap();
+    assert_eq!(details.author, "Jane Doe <jane@example.com>");
+    assert_eq!(details.committer, details.author);
+    assert_eq!(details.commit_timestamp, details.timestamp);
+
+    let john: get::Identity = "John Smith <john@example.com>".parse().unwrap();
+    fs::write(working_dir.join("file.txt"), "second").unwrap();
+    let second = repo
+        .commit_as(Some("by john"), Some(&john), at(1680961469))
+        .unwrap()
+        .commit;
+    let details = repo.show(&second, false).unwrap();
+    assert_eq!(details.author, "John Smith <john@example.com>");
+    assert_eq!(details.committer, "Jane Doe <jane@example.com>");
+    assert!(details.to_string().contains(
+        "author John Smith <john@example.com>\n\
+         date   2023-04-08 13:44:29 +0000\n\
+         committer Jane Doe <jane@example.com>\n"
+    ));
+
+    // Amend keeps the original author and the time of authoring.
+    let amended = repo
+        .amend(Some("by john, amended"), at(1680961569))
+        .unwrap();
+    let details = repo.show(&amended, false).unwrap();
+    assert_eq!(details.author, "John Smith <john@example.com>");
+    assert_eq!(details.timestamp, 1680961469);
+    assert_eq!(details.committer, "Jane Doe <jane@example.com>");
+    assert_eq!(details.commit_timestamp, 1680961569);
+
+    // So does cherry-pick.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("other.txt"), "other").unwrap();
+    let other = repo.commit(Some("other"), at(1680961669)).unwrap();
+    let picked = match repo.cherry_pick(&amended, at(1680961769)).unwrap() {
+        get::MergeOutcome::Merged { commit, .. } => commit,
+        outcome => panic!("commit expected, got {:?}", outcome),
+    };
+    let details = repo.show(&picked, false).unwrap();
+    assert_eq!(details.author, "John Smith <john@example.com>");
+    assert_eq!(details.timestamp, 1680961469);
+    assert_eq!(details.commit_timestamp, 1680961769);
+
+    // History is ordered by the time commits were made, the picked one is newer than a commit
+    // made before picking even though it was authored earlier.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("middle.txt"), "middle").unwrap();
+    let middle = repo.commit(Some("middle"), at(1680961719)).unwrap();
+    let merge = match repo.merge(&picked, at(1680961819)).unwrap() {
+        get::MergeOutcome::Merged { commit, .. } => commit,
+        outcome => panic!("merge commit expected, got {:?}", outcome),
+    };
+    let history: Vec<String> = repo
+        .log(&merge, get::LogOptions::default())
+        .unwrap()
+        .map(|c| c.unwrap().digest)
+        .collect();
+    assert_eq!(history, vec![merge, picked, middle, other, base.clone()]);
+
+    // Commit concluding a merge is authored by the given author too.
+    repo.restore(&base).unwrap();
+    fs::write(working_dir.join("file.txt"), "mine").unwrap();
+    repo.commit(Some("mine"), at(1680961869)).unwrap();
+    assert!(matches!(
+        repo.merge(&amended, at(1680961969)).unwrap(),
+        get::MergeOutcome::Conflicts { .. }
+    ));
+    fs::write(working_dir.join("file.txt"), "resolved").unwrap();
+    let merged = repo
+        .commit_as(None, Some(&john), at(1680962069))
+        .unwrap()
+        .commit;
+    let details = repo.show(&merged, false).unwrap();
+    assert_eq!(details.parents.len(), 2);
+    assert_eq!(details.author, "John Smith <john@example.com>");
+    assert_eq!(details.committer, "Jane Doe <jane@example.com>");
+
+    assert!("John <john@example.com".parse::<get::Identity>().is_err());
+}
+
+#[test]
+fn corrupt_objects() {
+    let repo_root = TempDir::new("get_app_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(working_dir.join("file.txt"), "content").unwrap();
+    fs::create_dir(working_dir.join("dir")).unwrap();
+    fs::write(working_dir.join("dir/inner.txt"), "inner").unwrap();
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let digest = repo.commit(Some("base"), at(1680961369)).unwrap();
+    let commit_path = working_dir.join(".get/objects/commit").join(&digest);
+
+    // Broken objects are reported as errors instead of panics.
+    let tree = repo
+        .ls_tree(&digest, false)
+        .unwrap()
+        .into_iter()
+        .find(|e| e.kind == get::ObjectKind::Tree)
+        .unwrap();
+    write_object(
+        &working_dir.join(".get/objects/tree").join(&tree.digest),
+        "blob\tabc\n",
+    );
+    assert!(matches!(
+        repo.status().unwrap_err(),
+        get::error::Error::InvalidTreeEntry(entry) if entry == "blob\tabc"
+    ));
+
+    write_object(&commit_path, "0000\nauthor\nyesterday\nbase\n");
+    let err = repo.show(&digest, false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("commit {digest} is corrupt: invalid timestamp \"yesterday\"")
+    );
+    assert!(matches!(
+        repo.resolve_rev("HEAD~1"),
+        Err(get::error::Error::CorruptObject { .. })
+    ));
+
+    write_object(&commit_path, "0000\nauthor\n");
+    let err = repo.show(&digest, false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("commit {digest} is corrupt: missing commit properties")
+    );
+
+    write_object(
+        &commit_path,
+        "0000\nauthor\n1680961369\nbase\nlink\tabc\tfile.txt",
+    );
+    let err = repo.ls_tree(&digest, false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid tree entry \"link\\tabc\\tfile.txt\", expected `type\\tdigest\\tname`"
+    );
+
+    write_object(&commit_path, "0000\nauthor\n1680961369\nbase\nfile.txt");
+    assert!(matches!(
+        repo.status().unwrap_err(),
+        get::error::Error::InvalidTreeEntry(entry) if entry == "file.txt"
+    ));
+
+    fs::write(working_dir.join(".get/LOG"), "broken entry\n").unwrap();
+    assert!(matches!(
+        repo.reflog().unwrap_err(),
+        get::error::Error::InvalidReflogEntry(entry) if entry == "broken entry"
+    ));
+
+    // Symlinks can't be committed.
+    std::os::unix::fs::symlink("file.txt", working_dir.join("link")).unwrap();
+    assert!(matches!(
+        repo.commit(Some("link"), at(1680961569)).unwrap_err(),
+        get::error::Error::UnsupportedFileType(path) if path == working_dir.join("link")
+    ));
+}
+
+#[test]
+fn json_results() {
+    let repo_root = TempDir::new("get_app_test").unwrap();
+    let working_dir = repo_root.path().to_owned();
+
+    fs::write(working_dir.join(".get.toml"), "author = \"Jane Doe\"\n").unwrap();
+    fs::write(working_dir.join("file.txt"), "first").unwrap();
+    let _ = get::Repo::init(&mut working_dir.clone()).expect("repo initialization failed");
+    let repo = get::Repo::try_from(&working_dir).expect("getting repo failed");
+    let digest = repo.commit(Some("base"), at(1680961369)).unwrap();
+    let blob = repo.show(&digest, false).unwrap().changes[0]
+        .new
+        .clone()
+        .unwrap();
+
+    assert_eq!(
+        serde_json::to_value(repo.show(&digest, false).unwrap()).unwrap(),
+        json!({
+            "digest": digest,
+            "parents": [],
+            "author": "Jane Doe",
+            "timestamp": 1680961369,
+            "committer": "Jane Doe",
+            "commit_timestamp": 1680961369,
+            "message": "base",
+            "changes": [{ "kind": "added", "path": "file.txt", "old": null, "new": blob }],
+            "patch": null,
+        })
+    );
+
+    fs::remove_file(working_dir.join("file.txt")).unwrap();
+    assert_eq!(
+        serde_json::to_value(repo.status().unwrap()).unwrap(),
+        json!([{ "kind": "deleted", "path": "file.txt", "old": blob, "new": null }])
+    );
+}
+
+fn write_object(path: &Path, content: &str) {
+    let mut encoder = GzEncoder::new(fs::File::create(path).unwrap(), Compression::default());
+    encoder.write_all(content.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+}
+
+fn at(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
 }
 
-fn modify_files(working_dir: &PathBuf) {
+fn modify_files(working_dir: &Path) {
     fs::write(
         working_dir.join("test_file.txt"),
         b"and now it is modified!",